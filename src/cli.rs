@@ -1,5 +1,11 @@
+use crate::{
+    location::Location,
+    shell::{MessageFormat, Shell},
+};
+
 use camino::Utf8PathBuf;
-use clap::{Args, Parser};
+use clap::{Args, Parser, Subcommand};
+use semver::{Version, VersionReq};
 use url::Url;
 
 #[derive(Parser, Debug)]
@@ -12,6 +18,15 @@ pub struct Cli {
     pub command: CargoInvocation,
 }
 
+impl Cli {
+    /// The shell messages should be reported with, as chosen by `--message-format`
+    pub fn shell(&self) -> Shell {
+        let CargoInvocation::Override(Override { message_format, .. }) = self.command;
+
+        Shell::new(message_format)
+    }
+}
+
 #[derive(Parser, Debug)]
 pub enum CargoInvocation {
     #[command(name = "override", about)]
@@ -20,36 +35,158 @@ pub enum CargoInvocation {
 }
 
 #[derive(Args, Debug)]
+#[command(subcommand_negates_reqs = true)]
 pub struct Override {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[command(flatten)]
     pub source: Source,
 
     #[command(flatten)]
     pub git: Git,
 
+    /// Clone the git source into this directory, and patch with the clone, so that it can be edited
+    #[arg(long, value_name = "DIR", requires = "git", conflicts_with = "dry_run")]
+    pub clone_to: Option<Utf8PathBuf>,
+
+    /// Create a branch with this name in the clone made with `--clone-to`
+    #[arg(long, value_name = "BRANCH", requires = "clone_to")]
+    pub new_branch: Option<String>,
+
+    /// Name of the package to patch with, when the source contains more than one.
+    /// By default, the package the project depends on is used
+    #[arg(long)]
+    pub package: Option<String>,
+
+    /// Patch every crate in the source's workspace that the project depends on
+    #[arg(long, conflicts_with = "package")]
+    pub all: bool,
+
     #[arg(long)]
     /// Name of the registry to use.
     /// Usually `cargo-override` can correctly determine which registry to use without needing this flag
     pub registry: Option<String>,
 
+    /// Index URL of the registry to use, for registries without a name in any cargo config.
+    /// The patch is written under the URL
+    #[arg(long, value_name = "URL", conflicts_with = "registry")]
+    pub index: Option<Url>,
+
     /// Path to the `Cargo.toml` file that needs patching.
     /// By default, `cargo-override` searches for the `Cargo.toml` file in the current directory or any parent directory
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub manifest_path: Option<Utf8PathBuf>,
 
     /// Assert that `Cargo.lock` will remain unchanged
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub locked: bool,
     /// Prevents cargo from accessing the network
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub offline: bool,
     /// Equivalent to specifying both --locked and --offline
-    #[arg(long)]
+    #[arg(long, global = true)]
     pub frozen: bool,
+    /// Override a cargo configuration value, like `cargo --config`
+    #[arg(long, global = true, value_name = "KEY=VALUE|PATH")]
+    pub config: Vec<String>,
+
+    /// Where patches are written to, and read from.
+    /// `manifest` is the workspace `Cargo.toml`, `config` is `.cargo/config.toml` in the workspace,
+    /// and `user` is `config.toml` in the cargo home directory
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub location: Location,
+
+    /// Format of the messages printed by `cargo-override`
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub message_format: MessageFormat,
+
+    /// Replace an existing patch for the crate
+    #[arg(long)]
+    pub replace: bool,
+
+    /// Keep the replaced patch in the file, commented out, so that it can be restored
+    #[arg(long, requires = "replace")]
+    pub keep_replaced: bool,
 
     /// Force the override, ignoring compatibility checks.
     #[arg(long)]
     pub force: bool,
+
+    /// Print the changes that would be made to `Cargo.toml`, without writing them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Remove an existing patch from `Cargo.toml`
+    Remove(Remove),
+    /// List the patches in `Cargo.toml`, and whether cargo uses them
+    List,
+    /// Copy the source of a dependency into a local directory, and patch it with that directory
+    Edit(Edit),
+    /// Disable a patch, keeping it in `Cargo.toml` so that it can be enabled again
+    Disable(Remove),
+    /// Enable a patch that was disabled
+    Enable(Remove),
+    /// Undo the changes made by the last commands, restoring the files they changed
+    Undo(Undo),
+    /// Apply or remove a group of patches defined in `[workspace.metadata.cargo-override.profiles]`
+    #[command(subcommand)]
+    Profile(Profile),
+}
+
+#[derive(Args, Debug)]
+pub struct Undo {
+    /// Number of commands to undo
+    #[arg(default_value_t = 1)]
+    pub count: usize,
+
+    /// Undo changes even if the files changed again afterwards, losing those changes
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Profile {
+    /// Patch every crate listed in a profile
+    Apply(ProfileName),
+    /// Remove the patches of every crate listed in a profile
+    Remove(ProfileName),
+}
+
+#[derive(Args, Debug)]
+pub struct ProfileName {
+    /// Name of the profile
+    pub name: String,
+}
+
+#[derive(Args, Debug)]
+#[command(disable_version_flag = true)]
+pub struct Edit {
+    /// Name of the crate to edit
+    pub name: String,
+
+    /// Version of the crate to edit.
+    /// Only needed when the project uses more than one version of it
+    #[arg(long)]
+    pub version: Option<Version>,
+
+    /// Directory to copy the source into. Defaults to `patches/<NAME>` in the workspace root
+    #[arg(long)]
+    pub dir: Option<Utf8PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct Remove {
+    /// Name of the patched crate
+    pub name: String,
+
+    #[arg(long)]
+    /// Name of the registry the patch is defined for.
+    /// Only needed when the crate is patched on more than one registry
+    pub registry: Option<String>,
 }
 
 #[derive(Args, Debug)]
@@ -62,6 +199,19 @@ pub struct Source {
     /// Git URL to source override from
     #[arg(long, value_name = "URI", group = "git-group")]
     pub git: Option<Url>,
+
+    /// Version requirement of the registry version to override with, for the crate chosen with `--package`.
+    /// A bare version, such as `1.2.3`, means exactly that version
+    #[arg(long, value_name = "REQ", requires = "package", value_parser = version_requirement)]
+    pub registry_version: Option<VersionReq>,
+}
+
+/// Parses a version requirement, treating a bare version as an exact requirement like `cargo install --version` does
+fn version_requirement(requirement: &str) -> Result<VersionReq, semver::Error> {
+    match Version::parse(requirement) {
+        Ok(_) => VersionReq::parse(&format!("={requirement}")),
+        Err(_) => VersionReq::parse(requirement),
+    }
 }
 
 #[derive(Args, Clone, Debug, Default)]
@@ -1,31 +1,226 @@
-use crate::{cli, CargoInvocation, Cli};
+use crate::{
+    cli,
+    error::{Code, Error},
+    location::Location,
+    CargoInvocation, Cli,
+};
 
-use anyhow::bail;
+use std::{env, ffi::OsString, fmt, io, path::Path};
+
+use anyhow::{bail, Context as _};
 use camino::Utf8PathBuf;
+use cargo::{core::shell::Shell, util::context::GlobalContext};
 use cargo_util_schemas::core::GitReference;
+use semver::{Version, VersionReq};
 use url::Url;
 
 pub struct Context {
     pub cargo: Cargo,
 
-    pub registry_hint: Option<String>,
-
     pub manifest_path: Option<Utf8PathBuf>,
 
-    pub mode: Mode,
+    pub location: Location,
 
-    pub force: bool,
+    pub operation: Operation,
 }
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub struct Cargo {
     pub locked: bool,
     pub offline: bool,
+    /// Config values passed with `--config`, as `KEY=VALUE` or the path of a config file
+    pub config: Vec<String>,
+}
+
+impl Cargo {
+    /// The arguments passing `--config` values on to cargo commands
+    pub fn config_args(&self) -> impl Iterator<Item = String> + '_ {
+        self.config
+            .iter()
+            .flat_map(|config| ["--config".to_owned(), config.clone()])
+    }
+
+    /// Loads the cargo config for `cwd` like cargo does, from every config file,
+    /// the environment, and `--config` values
+    pub fn global_context(&self, cwd: &Path) -> anyhow::Result<GlobalContext> {
+        let shell = Shell::from_write(Box::new(io::sink()));
+
+        let home = home::cargo_home_with_cwd(cwd).context(Error::new(
+            Code::CargoConfig,
+            "failed to find the cargo home directory",
+        ))?;
+
+        let mut global_context = GlobalContext::new(shell, cwd.to_path_buf(), home);
+
+        global_context
+            .configure(
+                0,
+                true,
+                None,
+                false,
+                self.locked,
+                self.offline,
+                &None,
+                &[],
+                &self.config,
+            )
+            .context(Error::new(
+                Code::CargoConfig,
+                "failed to load cargo configuration",
+            ))?;
+
+        Ok(global_context)
+    }
+
+    /// Registries defined as `CARGO_REGISTRIES_*` variables in the `[env]` config.
+    ///
+    /// Cargo only sets `[env]` for the programs it runs, so these are set on the cargo commands
+    /// `cargo-override` runs, for cargo to find the registries. Variables that are set already are kept,
+    /// unless the config forces them
+    pub fn registry_env(&self, cwd: &Path) -> anyhow::Result<Vec<(String, OsString)>> {
+        let global_context = self.global_context(cwd)?;
+
+        let env_config = global_context
+            .env_config()
+            .context(Error::new(Code::CargoConfig, "failed to get [env] config"))?;
+
+        Ok(env_config
+            .iter()
+            .filter(|(key, value)| {
+                key.starts_with("CARGO_REGISTRIES_")
+                    && (value.is_force() || env::var_os(key).is_none())
+            })
+            .map(|(key, value)| (key.clone(), value.resolve(&global_context).into_owned()))
+            .collect())
+    }
 }
 
+pub enum Operation {
+    Override(Override),
+    Remove {
+        name: String,
+
+        registry: Option<String>,
+    },
+    List,
+    Disable {
+        name: String,
+
+        registry: Option<String>,
+    },
+    Enable {
+        name: String,
+
+        registry: Option<String>,
+    },
+    Undo {
+        count: usize,
+
+        force: bool,
+    },
+    ApplyProfile {
+        name: String,
+    },
+    RemoveProfile {
+        name: String,
+    },
+    Edit {
+        name: String,
+
+        version: Option<Version>,
+
+        dir: Option<Utf8PathBuf>,
+    },
+}
+
+pub struct Override {
+    pub packages: Packages,
+
+    pub registry_hint: Option<String>,
+
+    /// Index URL passed with `--index`, used as the patch key
+    pub index: Option<String>,
+
+    pub mode: Mode,
+
+    pub clone_to: Option<Checkout>,
+
+    pub conflict: Conflict,
+
+    pub force: bool,
+
+    pub dry_run: bool,
+}
+
+/// The crates of a patch source to patch with
+pub enum Packages {
+    /// The crate the project depends on
+    Inferred,
+    /// The crate with the given name
+    Named(String),
+    /// Every crate the project depends on
+    All,
+    /// The crates with the given names, as listed in a profile
+    List(Vec<String>),
+}
+
+impl Packages {
+    /// The names of the crates that were asked for, if any
+    pub fn named(&self) -> Option<&[String]> {
+        match self {
+            Packages::Named(name) => Some(std::slice::from_ref(name)),
+            Packages::List(names) => Some(names),
+            Packages::Inferred | Packages::All => None,
+        }
+    }
+}
+
+/// What to do when the crate is already patched with a different source
+#[derive(Clone, Copy, Default)]
+pub enum Conflict {
+    #[default]
+    Refuse,
+    Replace,
+    /// Replace the patch, keeping the old one as a comment so that it can be restored
+    KeepReplaced,
+}
+
+/// Where to clone a git source to, before patching with the clone
+pub struct Checkout {
+    pub dir: Utf8PathBuf,
+    /// Name of a branch to create in the clone
+    pub branch: Option<String>,
+}
+
+#[derive(Clone)]
 pub enum Mode {
     Path(Utf8PathBuf),
-    Git { url: Url, reference: GitReference },
+    Git {
+        url: Url,
+        reference: GitReference,
+    },
+    /// A published version from crates.io, the only registry versions can be taken from
+    Registry {
+        requirement: VersionReq,
+    },
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Path(path) => write!(f, "path: {path}"),
+            Mode::Git { url, reference } => {
+                write!(f, "git: {url}")?;
+                match reference {
+                    GitReference::DefaultBranch => Ok(()),
+                    GitReference::Branch(branch) => write!(f, " (branch: {branch})"),
+                    GitReference::Tag(tag) => write!(f, " (tag: {tag})"),
+                    GitReference::Rev(rev) => write!(f, " (rev: {rev})"),
+                }
+            }
+            Mode::Registry { requirement } => write!(f, "version: {requirement}"),
+        }
+    }
 }
 
 impl TryFrom<Cli> for Context {
@@ -35,55 +230,147 @@ impl TryFrom<Cli> for Context {
         Cli {
             command:
                 CargoInvocation::Override(cli::Override {
+                    command,
                     locked,
                     offline,
                     frozen,
+                    config,
+                    location,
+                    message_format: _,
+                    package,
+                    all,
                     registry,
+                    index,
                     manifest_path,
-                    source: cli::Source { path, git },
+                    source:
+                        cli::Source {
+                            path,
+                            git,
+                            registry_version,
+                        },
                     git: cli::Git { branch, tag, rev },
+                    clone_to,
+                    new_branch,
+                    replace,
+                    keep_replaced,
                     force,
+                    dry_run,
                 }),
         }: Cli,
     ) -> Result<Self, Self::Error> {
         // `--frozen` implies `--locked` and `--offline`
         let [locked, offline] = [locked, offline].map(|f| f || frozen);
 
-        let cargo = Cargo { locked, offline };
+        let cargo = Cargo {
+            locked,
+            offline,
+            config,
+        };
 
-        let mode = match (git, path) {
-            (Some(git), None) => Mode::Git {
-                url: git,
-                reference: {
-                    match (branch, tag, rev) {
-                        (None, None, None) => GitReference::DefaultBranch,
-                        (Some(branch), None, None) => GitReference::Branch(branch),
-                        (None, Some(tag), None) => GitReference::Tag(tag),
-                        (None, None, Some(rev)) => GitReference::Rev(rev),
-                        _ => bail!("multiple git identifiers used. Only use one of `--branch`, `--tag` or `--rev`")
+        if command.is_some() && (path.is_some() || git.is_some()) {
+            bail!(Error::new(
+                Code::InvalidArguments,
+                "`--path` and `--git` can not be used with a subcommand"
+            ))
+        }
 
-                    }
-                },
-            },
-            (None, Some(path)) => Mode::Path(path),
-            (Some(_), Some(_)) => {
-                bail!("`--git` can not bot set at the same time as `--path`")
+        let operation = match command {
+            Some(cli::Command::Remove(cli::Remove { name, registry })) => {
+                Operation::Remove { name, registry }
+            }
+            Some(cli::Command::List) => Operation::List,
+            Some(cli::Command::Disable(cli::Remove { name, registry })) => {
+                Operation::Disable { name, registry }
             }
-            (None, None) => {
-                bail!("specify a package to patch with using `--path` or `--git`")
+            Some(cli::Command::Enable(cli::Remove { name, registry })) => {
+                Operation::Enable { name, registry }
+            }
+            Some(cli::Command::Undo(cli::Undo { count, force })) => {
+                Operation::Undo { count, force }
+            }
+            Some(cli::Command::Profile(cli::Profile::Apply(cli::ProfileName { name }))) => {
+                Operation::ApplyProfile { name }
+            }
+            Some(cli::Command::Profile(cli::Profile::Remove(cli::ProfileName { name }))) => {
+                Operation::RemoveProfile { name }
+            }
+            Some(cli::Command::Edit(cli::Edit { name, version, dir })) => {
+                Operation::Edit { name, version, dir }
+            }
+            None => {
+                let mode = match (git, path, registry_version) {
+                    (Some(git), None, None) => Mode::Git {
+                        url: git,
+                        reference: {
+                            match (branch, tag, rev) {
+                                (None, None, None) => GitReference::DefaultBranch,
+                                (Some(branch), None, None) => GitReference::Branch(branch),
+                                (None, Some(tag), None) => GitReference::Tag(tag),
+                                (None, None, Some(rev)) => GitReference::Rev(rev),
+                                _ => bail!(Error::new(Code::InvalidArguments, "multiple git identifiers used. Only use one of `--branch`, `--tag` or `--rev`"))
+
+                            }
+                        },
+                    },
+                    (None, Some(path), None) => Mode::Path(path),
+                    (None, None, Some(requirement)) => Mode::Registry { requirement },
+                    (Some(_), Some(_), _) => {
+                        bail!(Error::new(
+                            Code::InvalidArguments,
+                            "`--git` can not bot set at the same time as `--path`"
+                        ))
+                    }
+                    (_, _, Some(_)) => {
+                        bail!(Error::new(
+                            Code::InvalidArguments,
+                            "`--registry-version` can not be set at the same time as `--path` or `--git`"
+                        ))
+                    }
+                    (None, None, None) => {
+                        bail!(Error::new(
+                            Code::InvalidArguments,
+                            "specify a package to patch with using `--path`, `--git` or `--registry-version`"
+                        ))
+                    }
+                };
+
+                Operation::Override(Override {
+                    packages: match (package, all) {
+                        (Some(package), _) => Packages::Named(package),
+                        (None, true) => Packages::All,
+                        (None, false) => Packages::Inferred,
+                    },
+                    registry_hint: registry,
+                    index: index.map(String::from),
+
+                    mode,
+
+                    clone_to: clone_to.map(|dir| Checkout {
+                        dir,
+                        branch: new_branch,
+                    }),
+
+                    conflict: match (replace, keep_replaced) {
+                        (_, true) => Conflict::KeepReplaced,
+                        (true, false) => Conflict::Replace,
+                        (false, false) => Conflict::Refuse,
+                    },
+
+                    force,
+
+                    dry_run,
+                })
             }
         };
 
         Ok(Self {
             cargo,
 
-            registry_hint: registry,
-
             manifest_path,
 
-            mode,
+            location,
 
-            force,
+            operation,
         })
     }
 }
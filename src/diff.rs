@@ -0,0 +1,63 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use anstyle::{AnsiColor, Style};
+use pathdiff::diff_paths;
+use similar::{ChangeTag, TextDiff};
+
+/// Prints a unified diff between two versions of a file to stdout.
+///
+/// Colors are only used when stdout supports them.
+pub fn print(path: &Path, working_dir: &Path, old: &str, new: &str) {
+    let path = diff_paths(path, working_dir).unwrap_or_else(|| path.to_path_buf());
+
+    // Failing to write to stdout is not worth failing the whole command over
+    let _ = write(
+        &mut anstream::stdout().lock(),
+        &path.display().to_string(),
+        old,
+        new,
+    );
+}
+
+/// Renders a unified diff between two versions of a file, without colors
+pub fn unified(path: &Path, working_dir: &Path, old: &str, new: &str) -> String {
+    let path = diff_paths(path, working_dir).unwrap_or_else(|| path.to_path_buf());
+
+    let mut diff = Vec::new();
+
+    write(&mut diff, &path.display().to_string(), old, new)
+        .expect("writing to a `Vec` never fails");
+
+    anstream::adapter::strip_str(&String::from_utf8_lossy(&diff)).to_string()
+}
+
+fn write(w: &mut impl Write, path: &str, old: &str, new: &str) -> io::Result<()> {
+    let header = Style::new().bold();
+    let hunk_header = AnsiColor::Cyan.on_default();
+    let deletion = AnsiColor::Red.on_default();
+    let insertion = AnsiColor::Green.on_default();
+
+    writeln!(w, "{header}--- {path}{header:#}")?;
+    writeln!(w, "{header}+++ {path}{header:#}")?;
+
+    for hunk in TextDiff::from_lines(old, new).unified_diff().iter_hunks() {
+        writeln!(w, "{hunk_header}{}{hunk_header:#}", hunk.header())?;
+
+        for change in hunk.iter_changes() {
+            let (sign, style) = match change.tag() {
+                ChangeTag::Delete => ('-', deletion),
+                ChangeTag::Insert => ('+', insertion),
+                ChangeTag::Equal => (' ', Style::new()),
+            };
+
+            let line = change.value().trim_end_matches(['\r', '\n']);
+
+            writeln!(w, "{style}{sign}{line}{style:#}")?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use std::{
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use pathdiff::diff_paths;
+
+use serde::Serialize;
+
+/// Stable identifiers for the errors `cargo-override` reports.
+///
+/// These are part of the `--message-format json` output, so existing codes should never be renamed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Code {
+    /// Command line arguments were used in a combination that does not make sense
+    InvalidArguments,
+    /// Running `cargo metadata` failed
+    CargoMetadata,
+    /// Running `cargo update` failed
+    CargoUpdate,
+    /// Cargo configuration could not be loaded
+    CargoConfig,
+    /// A manifest could not be parsed or edited
+    InvalidManifest,
+    /// A file could not be read or written
+    Io,
+    /// A git source could not be fetched
+    GitSource,
+    /// A git repository could not be cloned with `--clone-to`
+    GitClone,
+    /// The versions of a crate could not be looked up in a registry index
+    RegistryQuery,
+    /// No package was found at the patch source
+    PackageNotFound,
+    /// More than one package was found at the patch source
+    MultiplePackages,
+    /// The project does not depend on the patched crate
+    DependencyNotFound,
+    /// The patch version does not meet the project's version requirements
+    VersionIncompatible,
+    /// The patch does not declare features the project enables on the crate
+    FeatureMissing,
+    /// The registry passed with `--registry` is not the one the dependency uses
+    RegistryMismatch,
+    /// The registry of a dependency could not be named
+    UnknownRegistry,
+    /// Cargo did not use the patch after it was written
+    PatchUnused,
+    /// No patch exists for the crate
+    PatchNotFound,
+    /// The crate is already patched with another source
+    PatchExists,
+    /// No profile with the name exists
+    ProfileNotFound,
+    /// The journal holds fewer changes than were asked to be undone
+    NothingToUndo,
+    /// A file changed since the change being undone, so undoing it would lose those changes
+    JournalConflict,
+    /// The crate is patched more than once, and it is unclear which patch is meant
+    AmbiguousPatch,
+    /// An error without a more specific code
+    Other,
+}
+
+/// An error message tagged with a [`Code`].
+///
+/// Can be used directly with `bail!`, or as context for another error.
+#[derive(Debug)]
+pub struct Error {
+    code: Code,
+    message: String,
+    snippet: Option<Snippet>,
+}
+
+impl Error {
+    pub fn new(code: Code, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            snippet: None,
+        }
+    }
+
+    /// Points the error at the part of a file that caused it
+    pub fn with_snippet(self, snippet: impl Into<Option<Snippet>>) -> Self {
+        Self {
+            snippet: snippet.into(),
+            ..self
+        }
+    }
+}
+
+/// Part of a file an error is about, rendered with the error like rustc's diagnostics
+#[derive(Debug)]
+pub struct Snippet {
+    pub path: PathBuf,
+    pub source: String,
+    /// Byte range of `source` to underline
+    pub span: Range<usize>,
+    pub label: String,
+    pub help: Option<String>,
+}
+
+impl Snippet {
+    /// The path is shown relative to `working_dir` when possible
+    pub fn new(
+        path: &Path,
+        working_dir: &Path,
+        source: impl Into<String>,
+        span: Range<usize>,
+        label: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: diff_paths(path, working_dir).unwrap_or_else(|| path.to_path_buf()),
+            source: source.into(),
+            span,
+            label: label.into(),
+            help: None,
+        }
+    }
+
+    pub fn help(self, help: impl Into<String>) -> Self {
+        Self {
+            help: Some(help.into()),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Finds the code of the outermost coded error in the chain
+pub fn code(error: &anyhow::Error) -> Code {
+    error
+        .downcast_ref::<Error>()
+        .map_or(Code::Other, |error| error.code)
+}
+
+/// Finds the snippet of the outermost coded error in the chain, if it has one
+pub fn snippet(error: &anyhow::Error) -> Option<&Snippet> {
+    error.downcast_ref::<Error>()?.snippet.as_ref()
+}
@@ -1,52 +1,176 @@
-use std::{
-    io,
-    path::{Path, PathBuf},
+use crate::{
+    context,
+    error::{Code, Error},
+    metadata::Crate,
 };
 
+use std::{ffi::OsStr, path::Path, process::Command};
+
 use anyhow::{bail, Context};
 use cargo::{
-    core::{shell::Shell, GitReference, SourceId},
+    core::{GitReference, SourceId},
     sources::git::GitSource,
-    util::{cache_lock::CacheLockMode, context::GlobalContext},
+    util::cache_lock::CacheLockMode,
 };
-use home::cargo_home_with_cwd;
 use url::Url;
 
-/// Fetches a git source with Cargo, and returns the path to that source in the cargo cache
+/// Fetches a git source with Cargo, and returns the crates it exposes.
+///
+/// When a package is named, only the crate with that name is returned.
 pub fn get_source(
     working_dir: &Path,
+    cargo: &context::Cargo,
     url: &Url,
     reference: GitReference,
-) -> anyhow::Result<PathBuf> {
-    let shell = Shell::from_write(Box::new(io::sink()));
+    packages: &context::Packages,
+) -> anyhow::Result<Vec<Crate>> {
+    let named = packages.named();
 
-    let global_context = GlobalContext::new(
-        shell,
-        working_dir.to_path_buf(),
-        cargo_home_with_cwd(working_dir).unwrap(),
-    );
+    let global_context = cargo.global_context(working_dir)?;
 
     let package_lock = global_context
         .acquire_package_cache_lock(CacheLockMode::DownloadExclusive)
         .unwrap();
 
     let mut git_source =
-        GitSource::new(SourceId::for_git(url, reference).unwrap(), &global_context)
-            .with_context(|| format!("failed to download git source. Is \"{url}\" a valid URL?"))?;
+        GitSource::new(SourceId::for_git(url, reference).unwrap(), &global_context).with_context(
+            || {
+                Error::new(
+                    Code::GitSource,
+                    format!("failed to download git source. Is \"{url}\" a valid URL?"),
+                )
+            },
+        )?;
 
     let packages = git_source.read_packages().with_context(|| {
-        format!("failed to read packages from git source. Does \"{url}\" contain a crate?")
+        Error::new(
+            Code::GitSource,
+            format!("failed to read packages from git source. Does \"{url}\" contain a crate?"),
+        )
     })?;
 
     drop(package_lock);
 
-    match packages[..] {
-        [] => {
-            bail!("git repo {url} does not expose any crates")
-        }
-        [_, _, ..] => {
-            bail!("multiple candidate packages found in git repo {url}")
-        }
-        [ref package] => Ok(package.root().to_path_buf()),
+    let packages = packages
+        .into_iter()
+        .filter(|candidate| {
+            named.map_or(true, |names| {
+                names.iter().any(|name| candidate.name().as_str() == name)
+            })
+        })
+        .map(|package| Crate {
+            name: package.name().to_string(),
+            version: package.version().clone(),
+            root: package.root().to_path_buf(),
+            features: Some(
+                package
+                    .summary()
+                    .features()
+                    .keys()
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+        })
+        .collect::<Vec<_>>();
+
+    let missing = named.and_then(|names| {
+        names
+            .iter()
+            .find(|name| packages.iter().all(|package| &package.name != *name))
+    });
+
+    if let Some(package) = missing {
+        bail!(Error::new(
+            Code::PackageNotFound,
+            format!("git repo {url} does not expose a crate named \"{package}\"")
+        ))
     }
+
+    if packages.is_empty() {
+        bail!(Error::new(
+            Code::PackageNotFound,
+            format!("git repo {url} does not expose any crates")
+        ))
+    }
+
+    Ok(packages)
+}
+
+/// Clones a repository with the `git` command line, checked out at `reference`.
+///
+/// Unlike the checkouts cargo keeps, the clone is a normal repository that can be edited,
+/// committed to and pushed.
+pub fn clone(
+    url: &Url,
+    reference: &GitReference,
+    dir: &Path,
+    new_branch: Option<&str>,
+) -> anyhow::Result<()> {
+    let branch = match reference {
+        GitReference::Branch(name) | GitReference::Tag(name) => Some(name.as_str()),
+        GitReference::DefaultBranch | GitReference::Rev(_) => None,
+    };
+
+    git(
+        None,
+        ["clone".as_ref(), url.as_str().as_ref()]
+            .into_iter()
+            .chain(
+                branch
+                    .into_iter()
+                    .flat_map(|branch| ["--branch", branch])
+                    .map(OsStr::new),
+            )
+            .chain(["--".as_ref(), dir.as_os_str()]),
+    )
+    .with_context(|| {
+        Error::new(
+            Code::GitClone,
+            format!("failed to clone \"{url}\" to \"{}\"", dir.display()),
+        )
+    })?;
+
+    if let GitReference::Rev(rev) = reference {
+        git(Some(dir), ["checkout", "--detach", rev]).with_context(|| {
+            Error::new(
+                Code::GitClone,
+                format!("failed to check out revision \"{rev}\" of \"{url}\""),
+            )
+        })?;
+    }
+
+    if let Some(new_branch) = new_branch {
+        git(Some(dir), ["checkout", "-b", new_branch]).with_context(|| {
+            Error::new(
+                Code::GitClone,
+                format!("failed to create branch \"{new_branch}\""),
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+fn git(
+    dir: Option<&Path>,
+    args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+) -> anyhow::Result<()> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    let output = command
+        .args(args)
+        .output()
+        .context("failed to start `git`")?;
+
+    if !output.status.success() {
+        bail!(
+            "`git` exited with an error: {}",
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        )
+    }
+
+    Ok(())
 }
@@ -0,0 +1,238 @@
+//! A history of the files `cargo-override` changes, so that changes can be undone byte for byte
+
+use crate::error::{Code, Error};
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context as _};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+
+/// Contents of files before a command runs
+pub struct Snapshot {
+    files: Vec<(PathBuf, Option<Vec<u8>>)>,
+}
+
+impl Snapshot {
+    pub fn take(paths: impl IntoIterator<Item = PathBuf>) -> anyhow::Result<Self> {
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let content = read(&path)?;
+                Ok((path, content))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self { files })
+    }
+}
+
+/// A command in the journal, along with the files it changed
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    command: String,
+    files: Vec<File>,
+}
+
+/// A changed file. The contents before and after the change are stored next to the entry
+#[derive(Serialize, Deserialize)]
+struct File {
+    path: PathBuf,
+    existed_before: bool,
+    exists_after: bool,
+}
+
+const ENTRY: &str = "entry.json";
+
+/// Adds the files that changed since `snapshot` was taken to the journal in `journal_dir`.
+///
+/// Nothing is recorded when no file changed.
+pub fn record(journal_dir: &Path, command: &str, snapshot: Snapshot) -> anyhow::Result<()> {
+    let mut changes = Vec::new();
+
+    for (path, before) in snapshot.files {
+        let after = read(&path)?;
+
+        if before != after {
+            changes.push((path, before, after));
+        }
+    }
+
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let id = entries(journal_dir)?.last().map_or(0, |(id, _)| id + 1);
+    let entry_dir = journal_dir.join(format!("{id:06}"));
+
+    let write = || -> io::Result<()> {
+        fs::create_dir_all(&entry_dir)?;
+
+        let mut files = Vec::new();
+
+        for (index, (path, before, after)) in changes.into_iter().enumerate() {
+            if let Some(before) = &before {
+                fs::write(entry_dir.join(format!("{index}.before")), before)?;
+            }
+            if let Some(after) = &after {
+                fs::write(entry_dir.join(format!("{index}.after")), after)?;
+            }
+
+            files.push(File {
+                path,
+                existed_before: before.is_some(),
+                exists_after: after.is_some(),
+            });
+        }
+
+        let entry = Entry {
+            command: command.to_owned(),
+            files,
+        };
+
+        fs::write(
+            entry_dir.join(ENTRY),
+            serde_json::to_string_pretty(&entry).expect("entries can always be serialized"),
+        )
+    };
+
+    write().with_context(|| {
+        Error::new(
+            Code::Io,
+            format!(
+                "failed to write to the journal in \"{}\"",
+                journal_dir.display()
+            ),
+        )
+    })
+}
+
+/// Restores the files changed by the last `count` commands in the journal, newest first.
+///
+/// Files that changed again since are only restored with `force`.
+/// Every entry is checked before any file is restored, so a conflict leaves every file as it was.
+/// Directories created along the way, such as copies made by `edit` or clones made with `--clone-to`,
+/// are not part of the journal and are left in place.
+/// Returns the commands that were undone.
+pub fn undo(journal_dir: &Path, count: usize, force: bool) -> anyhow::Result<Vec<String>> {
+    let entries = entries(journal_dir)?;
+
+    if entries.len() < count {
+        bail!(Error::new(
+            Code::NothingToUndo,
+            match entries.len() {
+                0 => "there is nothing to undo".to_owned(),
+                1 => "only 1 change can be undone".to_owned(),
+                available => format!("only {available} changes can be undone"),
+            }
+        ))
+    }
+
+    let entries = entries
+        .iter()
+        .rev()
+        .take(count)
+        .map(|(_, entry_dir)| {
+            let entry: Entry = serde_json::from_str(&fs::read_to_string(entry_dir.join(ENTRY))?)
+                .context(Error::new(
+                    Code::Io,
+                    format!("journal entry in \"{}\" is corrupt", entry_dir.display()),
+                ))?;
+
+            Ok((entry_dir, entry))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    // What each file holds once the newer entries are undone
+    let mut restored = HashMap::<&Path, Option<Vec<u8>>>::new();
+
+    for (entry_dir, entry) in &entries {
+        for (index, file) in entry.files.iter().enumerate() {
+            let current = match restored.remove(file.path.as_path()) {
+                Some(content) => content,
+                None => read(&file.path)?,
+            };
+
+            if !force && current != stored(entry_dir, index, "after", file.exists_after)? {
+                bail!(Error::new(
+                    Code::JournalConflict,
+                    format!(
+                        "\"{}\" changed after `{}`. Use `--force` to undo it anyway",
+                        file.path.display(),
+                        entry.command
+                    )
+                ))
+            }
+
+            restored.insert(
+                &file.path,
+                stored(entry_dir, index, "before", file.existed_before)?,
+            );
+        }
+    }
+
+    for (path, content) in restored {
+        match content {
+            Some(content) => fs::write(path, content)?,
+            None if path.exists() => fs::remove_file(path)?,
+            None => {}
+        }
+    }
+
+    let mut undone = Vec::new();
+
+    for (entry_dir, entry) in entries {
+        fs::remove_dir_all(entry_dir)?;
+
+        undone.push(entry.command);
+    }
+
+    Ok(undone)
+}
+
+/// Reads the contents of the file at `index` in an entry, as it was `before` or `after` the command
+fn stored(
+    entry_dir: &Path,
+    index: usize,
+    state: &str,
+    exists: bool,
+) -> io::Result<Option<Vec<u8>>> {
+    exists
+        .then(|| fs::read(entry_dir.join(format!("{index}.{state}"))))
+        .transpose()
+}
+
+/// Lists the entries in the journal, oldest first
+fn entries(journal_dir: &Path) -> anyhow::Result<Vec<(u64, PathBuf)>> {
+    if !journal_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = fs::read_dir(journal_dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|path| {
+            let id = path.file_name()?.to_str()?.parse().ok()?;
+            Some((id, path))
+        })
+        .collect::<Vec<_>>();
+
+    entries.sort();
+
+    Ok(entries)
+}
+
+fn read(path: &Path) -> anyhow::Result<Option<Vec<u8>>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    fs::read(path)
+        .map(Some)
+        .with_context(|| Error::new(Code::Io, format!("failed to read \"{}\"", path.display())))
+}
@@ -1,38 +1,53 @@
 pub mod cli;
+mod diff;
+mod error;
 mod git;
+mod journal;
+mod location;
 pub mod registry;
 
 mod context;
 mod metadata;
+mod shell;
 mod toml;
 
 pub use cli::{CargoInvocation, Cli};
 pub use context::Context;
+pub use location::Location;
+pub use shell::{MessageFormat, Shell};
 
-use std::path::{Path, PathBuf};
+use error::{Code, Error};
+use shell::{LockChange, Message};
+
+use std::{
+    io, path,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{bail, ensure, Context as _};
+use camino::Utf8PathBuf;
 use fs_err as fs;
+use pathdiff::diff_paths;
 
 pub static DEFAULT_REGISTRY: &str = "crates-io";
 pub static DEFAULT_REGISTRY_URL: &str = "https://github.com/rust-lang/crates.io-index";
+/// The sparse index of crates.io.
+///
+/// Cargo treats it as a different source from [`DEFAULT_REGISTRY_URL`],
+/// which allows crates.io dependencies to be patched with other versions from crates.io.
+pub static CRATES_IO_SPARSE_INDEX: &str = "sparse+https://index.crates.io/";
 pub static CARGO_TOML: &str = "Cargo.toml";
+pub static CARGO_LOCK: &str = "Cargo.lock";
 
-pub fn run(working_dir: &Path, args: Cli) -> anyhow::Result<()> {
+pub fn run(working_dir: &Path, args: Cli, shell: Shell) -> anyhow::Result<()> {
     let Context {
         cargo,
         manifest_path,
-        registry_hint,
-        mode,
-        force,
+        location,
+        operation,
     } = args.try_into()?;
 
-    let path = match &mode {
-        context::Mode::Path(ref path) => working_dir.join(path),
-        context::Mode::Git { url, reference } => {
-            git::get_source(working_dir, url, reference.clone())?
-        }
-    };
+    let cargo = &cargo;
 
     let manifest_dir = manifest_path.map(|mut path| {
         path.pop();
@@ -44,53 +59,683 @@ pub fn run(working_dir: &Path, args: Cli) -> anyhow::Result<()> {
         .map(|path| path.as_path().as_std_path())
         .unwrap_or(working_dir);
 
-    let patch_manifest = metadata::crate_details(&path, cargo)?;
+    // Taking a snapshot fails when the project can not be found,
+    // in which case the operation fails too, and there is nothing to record
+    let snapshot = match operation {
+        context::Operation::List | context::Operation::Undo { .. } => None,
+        _ => take_snapshot(working_dir, manifest_dir, cargo, location).ok(),
+    };
+
+    let result = match operation {
+        context::Operation::Override(operation) => {
+            override_dependency(working_dir, manifest_dir, cargo, location, shell, operation)
+        }
+        context::Operation::Remove { name, registry } => remove_override(
+            working_dir,
+            manifest_dir,
+            cargo,
+            location,
+            shell,
+            &name,
+            registry.as_deref(),
+        ),
+        context::Operation::Disable { name, registry } => toggle_override(
+            working_dir,
+            manifest_dir,
+            cargo,
+            location,
+            shell,
+            &name,
+            registry.as_deref(),
+            false,
+        ),
+        context::Operation::Enable { name, registry } => toggle_override(
+            working_dir,
+            manifest_dir,
+            cargo,
+            location,
+            shell,
+            &name,
+            registry.as_deref(),
+            true,
+        ),
+        context::Operation::ApplyProfile { name } => {
+            apply_profile(working_dir, manifest_dir, cargo, location, shell, &name)
+        }
+        context::Operation::RemoveProfile { name } => {
+            remove_profile(working_dir, manifest_dir, cargo, location, shell, &name)
+        }
+        context::Operation::List => {
+            list_overrides(working_dir, manifest_dir, cargo, location, shell)
+        }
+        context::Operation::Edit { name, version, dir } => edit_dependency(
+            working_dir,
+            manifest_dir,
+            cargo,
+            location,
+            shell,
+            &name,
+            version.as_ref(),
+            dir,
+        ),
+        context::Operation::Undo { count, force } => undo(manifest_dir, cargo, shell, count, force),
+    };
+
+    if let (Ok(()), Some((journal_dir, snapshot))) = (&result, snapshot) {
+        let command = std::iter::once("cargo".to_owned())
+            .chain(std::env::args().skip(1))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if let Err(e) = journal::record(&journal_dir, &command, snapshot) {
+            shell.warn(format_args!(
+                "unable to record the change, so it can not be undone: {e:?}"
+            ));
+        }
+    }
+
+    result
+}
+
+/// Records the files an operation may change, along with the journal they are recorded in
+fn take_snapshot(
+    working_dir: &Path,
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    location: Location,
+) -> anyhow::Result<(PathBuf, journal::Snapshot)> {
+    let manifest_path = project_manifest(manifest_dir, cargo)?;
+    let patch_path = location.file(&manifest_path, working_dir)?;
+    let lock_path = manifest_path.with_file_name(CARGO_LOCK);
+
+    let snapshot = journal::Snapshot::take([patch_path, lock_path])?;
+
+    Ok((journal_dir(manifest_dir, cargo)?, snapshot))
+}
+
+fn journal_dir(manifest_dir: &Path, cargo: &context::Cargo) -> anyhow::Result<PathBuf> {
+    Ok(metadata::target_directory(manifest_dir, cargo)?
+        .join("cargo-override")
+        .join("journal"))
+}
+
+/// Undoes the last `count` commands recorded in the journal
+fn undo(
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    shell: Shell,
+    count: usize,
+    force: bool,
+) -> anyhow::Result<()> {
+    let undone = journal::undo(&journal_dir(manifest_dir, cargo)?, count, force)?;
+
+    for command in &undone {
+        shell.print(&Message::Undone { command });
+    }
+
+    Ok(())
+}
+
+/// A patch that is about to be written
+struct PendingPatch {
+    krate: metadata::Crate,
+    mode: context::Mode,
+    registry: String,
+}
+
+fn override_dependency(
+    working_dir: &Path,
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    location: Location,
+    shell: Shell,
+    context::Override {
+        packages,
+        registry_hint,
+        index,
+        mode,
+        clone_to,
+        conflict,
+        force,
+        dry_run,
+    }: context::Override,
+) -> anyhow::Result<()> {
+    if let (context::Mode::Git { url, reference }, Some(checkout)) = (&mode, clone_to) {
+        let target = working_dir.join(&checkout.dir);
+
+        let mode = clone_source(working_dir, shell, url, reference, checkout)?;
+
+        let result = override_dependency(
+            working_dir,
+            manifest_dir,
+            cargo,
+            location,
+            shell,
+            context::Override {
+                packages,
+                registry_hint,
+                index,
+                mode,
+                clone_to: None,
+                conflict,
+                force,
+                dry_run,
+            },
+        );
+
+        // The clone would get in the way of trying again
+        if result.is_err() {
+            let _ = fs::remove_dir_all(&target);
+        }
+
+        return result;
+    }
+
+    let candidates = match &mode {
+        context::Mode::Path(ref path) => {
+            metadata::crate_details(working_dir.join(path), cargo, &packages)?
+        }
+        context::Mode::Git { url, reference } => {
+            git::get_source(working_dir, cargo, url, reference.clone(), &packages)?
+        }
+        context::Mode::Registry { requirement } => {
+            let Some(names) = packages.named() else {
+                bail!(Error::new(
+                    Code::InvalidArguments,
+                    "`--registry-version` needs the crate to override, passed with `--package`"
+                ))
+            };
+
+            names
+                .iter()
+                .map(|name| {
+                    Ok(metadata::Crate {
+                        name: name.clone(),
+                        version: registry::newest_version(working_dir, cargo, name, requirement)?,
+                        // Registry crates have no local source
+                        root: PathBuf::new(),
+                        features: None,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?
+        }
+    };
 
     let manifest_path = project_manifest(manifest_dir, cargo)?;
 
+    let project_path = {
+        let mut manifest_path = manifest_path.clone();
+        manifest_path.pop();
+        manifest_path
+    };
+
+    let lock_path = project_path.join(CARGO_LOCK);
+
+    // Taken before dependencies are resolved, which creates or updates `Cargo.lock`
+    let mut lock_restore = LockRestore::new(lock_path.clone());
+
     let project_deps = metadata::direct_dependencies(manifest_dir, cargo)
         .context("failed to get dependencies for current project")?;
 
+    // Without a resolved graph, only the requirements of direct dependencies can be checked
+    let edges = metadata::dependency_edges(manifest_dir, cargo).unwrap_or_default();
+
+    let crates = match packages {
+        context::Packages::All => select_all_crates(candidates, &project_deps, || {
+            metadata::resolved_dependencies(manifest_dir, cargo)
+        })?,
+        context::Packages::Inferred | context::Packages::Named(_) => {
+            vec![select_crate(
+                shell,
+                candidates,
+                &project_deps,
+                &edges,
+                || metadata::resolved_dependencies(manifest_dir, cargo),
+            )?]
+        }
+        // Every crate of a profile is patched, as each one was listed on purpose
+        context::Packages::List(_) => candidates,
+    };
+
+    let patch_path = location.file(&manifest_path, working_dir)?;
+
+    let original_content = read_patch_file(&patch_path, location)?;
+    let project_manifest_content = original_content.clone().unwrap_or_default();
+
+    let patches = crates
+        .into_iter()
+        .map(|krate| {
+            let mode = member_mode(working_dir, &mode, &krate)?;
+            let registry = patch_registry(
+                working_dir,
+                manifest_dir,
+                cargo,
+                shell,
+                &project_deps,
+                &edges,
+                &krate,
+                &project_manifest_content,
+                registry_hint.as_deref(),
+                index.as_deref(),
+                force,
+            )?;
+
+            Ok(PendingPatch {
+                krate,
+                mode,
+                registry,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if let Some(patch) = patches.iter().find(|patch| {
+        matches!(patch.mode, context::Mode::Registry { .. }) && patch.registry != DEFAULT_REGISTRY
+    }) {
+        bail!(Error::new(
+            Code::UnknownRegistry,
+            format!(
+                "unable to override \"{}\" with a version from registry `{}`. \
+                 Overriding with another version is only supported for crates.io",
+                patch.krate.name, patch.registry
+            )
+        ))
+    }
+
+    let relative_to = location.relative_to(&patch_path);
+
+    let project_manifest_toml =
+        patches
+            .iter()
+            .try_fold(project_manifest_content.clone(), |manifest, patch| {
+                toml::patch_manifest(
+                    working_dir,
+                    &patch_path,
+                    &manifest,
+                    relative_to,
+                    &patch.krate.name,
+                    &patch.registry,
+                    &patch.mode,
+                    conflict,
+                )
+            })?;
+
+    if dry_run {
+        if shell.format() == MessageFormat::Human {
+            diff::print(
+                &patch_path,
+                working_dir,
+                &project_manifest_content,
+                &project_manifest_toml,
+            );
+        }
+
+        for patch in &patches {
+            shell.print(&Message::PatchPreview {
+                krate: &patch.krate,
+                registry: &patch.registry,
+                source: toml::source_fields(working_dir, relative_to, &patch.mode),
+                manifest_path: &patch_path,
+                diff: diff::unified(
+                    &patch_path,
+                    working_dir,
+                    &project_manifest_content,
+                    &project_manifest_toml,
+                ),
+            });
+        }
+
+        return Ok(());
+    }
+
+    let lock_content = fs::read_to_string(&lock_path).ok();
+
+    write_patch_file(&patch_path, &project_manifest_toml)?;
+
+    let update_command = patches
+        .iter()
+        .map(|patch| format!("-p {}", patch.krate.name))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if cargo.locked {
+        shell.warn(format_args!(
+            "`Cargo.lock` was not updated and cargo may not use the patch yet, \
+             because `--locked` prevents `Cargo.lock` from being updated. \
+             Run `cargo update {update_command}` to update it"
+        ));
+    } else {
+        match verify_patches(
+            working_dir,
+            &project_path,
+            cargo,
+            &patches,
+            lock_content.as_deref(),
+        ) {
+            Ok(unused) if unused.is_empty() => {}
+            Ok(unused) if force => {
+                for patch in unused {
+                    shell.warn(format_args!(
+                        "cargo does not use the patch for \"{}\". \
+                         The patch was kept because `--force` was passed",
+                        patch.krate.name
+                    ))
+                }
+            }
+            Ok(unused) => {
+                match original_content {
+                    Some(content) => fs::write(&patch_path, content),
+                    None => fs::remove_file(&patch_path),
+                }
+                .with_context(|| {
+                    Error::new(
+                        Code::Io,
+                        format!("failed to restore \"{}\"", patch_path.display()),
+                    )
+                })?;
+
+                lock_restore
+                    .restore()
+                    .context(Error::new(Code::Io, "failed to restore `Cargo.lock` file"))?;
+
+                let message = match unused[..] {
+                    [patch] => format!(
+                        "patch could not be applied because cargo does not use it. \
+                         Check that version {} of \"{}\" is compatible with every crate that depends on it",
+                        patch.krate.version, patch.krate.name,
+                    ),
+                    _ => format!(
+                        "patches could not be applied because cargo does not use the patches for {}. \
+                         Check that their versions are compatible with every crate that depends on them",
+                        unused
+                            .iter()
+                            .map(|patch| format!("\"{}\"", patch.krate.name))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    ),
+                };
+
+                bail!(Error::new(Code::PatchUnused, message))
+            }
+            Err(e) if cargo.offline => shell.warn(format_args!(
+                "unable to verify that cargo uses the patch, \
+                 because dependencies could not be resolved without accessing the network: {e:#}. \
+                 `--offline` prevents crates that are not downloaded yet from being fetched. \
+                 Run `cargo update {update_command}` without `--offline` to fetch them"
+            )),
+            Err(e) => shell.warn(format_args!(
+                "unable to verify that cargo uses the patch, \
+                 because dependencies could not be resolved: {e:#}"
+            )),
+        }
+    }
+
+    lock_restore.keep();
+
+    for patch in &patches {
+        shell.print(&Message::PatchApplied {
+            krate: &patch.krate,
+            registry: &patch.registry,
+            source: toml::source_fields(working_dir, relative_to, &patch.mode),
+            manifest_path: &patch_path,
+        });
+    }
+
+    match &lock_content {
+        Some(lock_content) if !cargo.locked => {
+            report_lock_changes(shell, cargo, &lock_path, lock_content, &patches)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Puts `Cargo.lock` back as it was when dropped, unless it is kept.
+///
+/// Resolving dependencies creates or updates `Cargo.lock`,
+/// which should only last when a patch was written
+struct LockRestore {
+    path: PathBuf,
+    original: Option<String>,
+    keep: bool,
+}
+
+impl LockRestore {
+    fn new(path: PathBuf) -> Self {
+        let original = fs::read_to_string(&path).ok();
+
+        Self {
+            path,
+            original,
+            keep: false,
+        }
+    }
+
+    fn restore(&self) -> std::io::Result<()> {
+        if fs::read_to_string(&self.path).ok() == self.original {
+            return Ok(());
+        }
+
+        match &self.original {
+            Some(original) => fs::write(&self.path, original),
+            None => fs::remove_file(&self.path),
+        }
+    }
+
+    fn keep(&mut self) {
+        self.keep = true;
+    }
+}
+
+impl Drop for LockRestore {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = self.restore();
+        }
+    }
+}
+
+/// When the path points at a workspace, patch with the directory of the chosen member.
+///
+/// Registry versions are pinned to the version that was found,
+/// as cargo refuses patches that match more than one version
+fn member_mode(
+    working_dir: &Path,
+    mode: &context::Mode,
+    krate: &metadata::Crate,
+) -> anyhow::Result<context::Mode> {
+    Ok(match mode {
+        context::Mode::Path(path) => {
+            let source_root = working_dir.join(path);
+            let member = diff_paths(
+                fs::canonicalize(&krate.root)?,
+                fs::canonicalize(&source_root)?,
+            )
+            .and_then(|member| Utf8PathBuf::try_from(member).ok())
+            .filter(|member| !member.as_str().is_empty());
+
+            context::Mode::Path(match member {
+                Some(member) => path.join(member),
+                None => path.clone(),
+            })
+        }
+        context::Mode::Registry { .. } => context::Mode::Registry {
+            requirement: semver::VersionReq::parse(&format!("={}", krate.version))?,
+        },
+        mode => mode.clone(),
+    })
+}
+
+/// Points at where a direct dependency is declared, for errors about it
+fn dependency_snippet(
+    working_dir: &Path,
+    dependency: &metadata::Dependency,
+    label: String,
+) -> Option<error::Snippet> {
+    let manifest_path = dependency.manifest_path.as_deref()?;
+    let manifest = fs::read_to_string(manifest_path).ok()?;
+    let span = toml::dependency_span(&manifest, &dependency.name)?;
+
+    Some(error::Snippet::new(
+        manifest_path,
+        working_dir,
+        manifest,
+        span,
+        label,
+    ))
+}
+
+/// Finds the dependency a crate would patch, and works out which registry the patch belongs to.
+///
+/// For git dependencies, the repository URL is used in place of a registry name.
+#[allow(clippy::too_many_arguments)]
+fn patch_registry(
+    working_dir: &Path,
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    shell: Shell,
+    project_deps: &[metadata::Dependency],
+    edges: &[metadata::Edge],
+    patch_manifest: &metadata::Crate,
+    patch_file: &str,
+    registry_hint: Option<&str>,
+    index: Option<&str>,
+    force: bool,
+) -> anyhow::Result<String> {
     let mut direct_deps = project_deps
         .iter()
         .filter(|dep| dep.name == patch_manifest.name)
         .peekable();
 
-    let dependency = if direct_deps.peek().is_some() {
-        direct_deps
-            .find(|dep| {
+    let resolved_deps;
+
+    let dependency = if let Some(first_dep) = direct_deps.peek().copied() {
+        let matching = direct_deps
+            .filter(|dep| {
                 dep.requirement
                     .as_ref()
                     .is_some_and(|req| req.matches(&patch_manifest.version) || force)
             })
-            .context("patch could not be applied because version is incompatible")?
+            .collect::<Vec<_>>();
+
+        if matching.is_empty() {
+            let requirement = first_dep
+                .requirement
+                .as_ref()
+                .map_or_else(|| "*".to_owned(), ToString::to_string);
+
+            bail!(Error::new(
+                Code::VersionIncompatible,
+                "patch could not be applied because version is incompatible",
+            )
+            .with_snippet(
+                dependency_snippet(working_dir, first_dep, format!("requires `{requirement}`"))
+                    .map(|snippet| {
+                        snippet.help(format!(
+                            "the patch has version {}. Use `--force` to apply it anyway",
+                            patch_manifest.version
+                        ))
+                    }),
+            ))
+        }
+
+        choose_dependency(shell, working_dir, edges, matching)?
     } else {
-        let resolved_deps = metadata::resolved_dependencies(manifest_dir, cargo)
+        resolved_deps = metadata::resolved_dependencies(manifest_dir, cargo)
             .context("failed to get dependencies for current project")?;
 
-        &resolved_deps
-            .into_iter()
-            .find(|dep| dep.name == patch_manifest.name)
-            .with_context(|| {
+        let matching = resolved_deps
+            .iter()
+            .filter(|dep| dep.name == patch_manifest.name)
+            .collect::<Vec<_>>();
+
+        ensure!(
+            !matching.is_empty(),
+            Error::new(
+                Code::DependencyNotFound,
                 format!(
                     "Unable to find dependency on crate \"{}\"",
                     patch_manifest.name
-                )
-            })?
-    };
+                ),
+            )
+        );
 
-    let dependency_registry = if dependency.registry == Some(DEFAULT_REGISTRY_URL.to_owned()) {
-        None
-    } else {
-        dependency.registry.as_deref()
+        choose_dependency(shell, working_dir, edges, matching)?
     };
 
+    if !force {
+        check_dependents(shell, edges, dependency, patch_manifest)?;
+    }
+
+    // A crate that is already patched with a local path resolves to that path,
+    // so the patch being replaced tells which registry it comes from
+    if dependency.path {
+        return toml::patched_registry(patch_file, &dependency.name, index.or(registry_hint));
+    }
+
+    // Registries without a name are patched with a table named after their index URL
+    if let Some(index) = index {
+        let source = dependency_source(dependency);
+
+        ensure!(
+            force || registry::same_index(index, source),
+            Error::new(
+                Code::RegistryMismatch,
+                format!(
+                    "user provided index `{index}` with the `--index` flag \
+                     but dependency `{}` comes from `{source}`",
+                    dependency.name
+                )
+            )
+            .with_snippet(dependency_snippet(
+                working_dir,
+                dependency,
+                format!("comes from `{source}`")
+            ))
+        );
+
+        return Ok(index.to_owned());
+    }
+
+    // Git dependencies are patched with a table named after the repository URL
+    if let Some(git) = &dependency.git {
+        return match registry_hint {
+            Some(registry) if registry != git => {
+                ensure!(
+                    force,
+                    Error::new(
+                        Code::RegistryMismatch,
+                        format!(
+                            "user provided registry `{}` with the `--registry` flag \
+                             but dependency `{}` comes from the git repository `{}`",
+                            registry, dependency.name, git
+                        )
+                    )
+                    .with_snippet(dependency_snippet(
+                        working_dir,
+                        dependency,
+                        format!("comes from `{git}`")
+                    ))
+                );
+                Ok(registry.to_owned())
+            }
+            _ => Ok(git.clone()),
+        };
+    }
+
+    let dependency_registry = dependency
+        .registry
+        .as_deref()
+        .filter(|registry| !registry::is_crates_io(registry));
+
     let registry = if let Some(registry_url) = &dependency_registry {
-        let registry_guess =
-            registry::get_registry_name_from_url(manifest_dir.to_path_buf(), registry_url)
-                .context("failed to guess registry")?;
+        let registry_guess = registry::get_registry_name_from_url(
+            manifest_dir,
+            cargo,
+            registry_url,
+            dependency.manifest_path.is_none(),
+        )
+        .context("failed to guess registry")?;
 
-        match (registry_hint.to_owned(), registry_guess) {
+        match (registry_hint.map(str::to_owned), registry_guess) {
             (Some(registry), None) => registry,
             (None, Some(registry)) => registry,
             (Some(registry_flag), Some(registry_guess)) if registry_guess == registry_flag => {
@@ -99,68 +744,907 @@ pub fn run(working_dir: &Path, args: Cli) -> anyhow::Result<()> {
             (Some(registry_flag), Some(registry_guess)) => {
                 ensure!(
                     force,
-                    "user provided registry `{}` with the `--registry` flag \
-                     but dependency `{}` \
-                     uses registry `{}`. 
+                    Error::new(
+                        Code::RegistryMismatch,
+                        format!(
+                            "user provided registry `{}` with the `--registry` flag \
+                             but dependency `{}` \
+                             uses registry `{}`. 
                      To use the registry, you passed, use `--force`",
-                    registry_flag,
-                    dependency.name,
-                    registry_guess
+                            registry_flag, dependency.name, registry_guess
+                        )
+                    )
+                    .with_snippet(dependency_snippet(
+                        working_dir,
+                        dependency,
+                        format!("uses registry `{registry_guess}`")
+                    ))
                 );
                 registry_flag
             }
-            (None, None) => bail!(
-                "unable to determine registry name for `{}`
-                 provide it using the `--registry` flag",
-                registry_url
-            ),
+            (None, None) => bail!(Error::new(
+                Code::UnknownRegistry,
+                format!(
+                    "unable to determine registry name for `{}`
+                 provide it using the `--registry` flag, or patch it by index URL with `--index`",
+                    registry_url
+                )
+            )),
         }
     } else {
         if let Some(registry) = registry_hint {
             if registry != DEFAULT_REGISTRY {
-                bail!(
-                    "user provided registry `{}` with the `--registry` flag \
-                     but dependency `{}` \
-                     uses the default registry `{}`",
-                    registry,
-                    dependency.name,
-                    DEFAULT_REGISTRY,
+                bail!(Error::new(
+                    Code::RegistryMismatch,
+                    format!(
+                        "user provided registry `{}` with the `--registry` flag \
+                         but dependency `{}` \
+                         uses the default registry `{}`",
+                        registry, dependency.name, DEFAULT_REGISTRY,
+                    )
                 )
+                .with_snippet(dependency_snippet(
+                    working_dir,
+                    dependency,
+                    format!("uses the default registry `{DEFAULT_REGISTRY}`")
+                )))
             };
         }
         DEFAULT_REGISTRY.to_owned()
     };
 
-    let project_manifest_content =
-        fs::read_to_string(&manifest_path).context("failed to read patch manifest")?;
+    Ok(registry)
+}
+
+/// The source a dependency comes from, as a git repository or registry index URL
+fn dependency_source(dependency: &metadata::Dependency) -> &str {
+    dependency
+        .git
+        .as_deref()
+        .or(dependency.registry.as_deref())
+        .unwrap_or(DEFAULT_REGISTRY_URL)
+}
 
-    let project_path = {
-        let mut manifest_path = manifest_path.clone();
-        manifest_path.pop();
-        manifest_path
+/// Picks the dependency a patch is for, when the crate comes from more than one source,
+/// or in versions that are not compatible with each other.
+///
+/// The user is asked to choose if the shell is interactive. Otherwise, the first dependency is used.
+fn choose_dependency<'a>(
+    shell: Shell,
+    working_dir: &Path,
+    edges: &[metadata::Edge],
+    mut dependencies: Vec<&'a metadata::Dependency>,
+) -> anyhow::Result<&'a metadata::Dependency> {
+    let mut sources = Vec::<&metadata::Dependency>::new();
+
+    for dependency in &dependencies {
+        let source = dependency_source(dependency);
+        if !sources.iter().any(|other| {
+            registry::same_index(dependency_source(other), source)
+                && compatible_versions(other) == compatible_versions(dependency)
+        }) {
+            sources.push(dependency);
+        }
+    }
+
+    if let [_, _, ..] = sources[..] {
+        let options = sources
+            .iter()
+            .map(|dependency| {
+                let mut option = dependency.name.clone();
+
+                if let Some(version) = &dependency.version {
+                    option.push_str(&format!(" {version}"));
+                }
+                option.push_str(&format!(" from `{}`", dependency_source(dependency)));
+
+                if let Some(requirement) = &dependency.requirement {
+                    option.push_str(&format!(", requiring `{requirement}`"));
+                }
+                if let Some(manifest_path) = &dependency.manifest_path {
+                    let manifest_path = diff_paths(manifest_path, working_dir)
+                        .unwrap_or_else(|| manifest_path.clone());
+                    option.push_str(&format!(", in \"{}\"", manifest_path.display()));
+                } else {
+                    let dependents = dependents(edges, &dependency.name);
+                    if !dependents.is_empty() {
+                        option.push_str(&format!(", depended on by {dependents}"));
+                    }
+                }
+
+                option
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(choice) = shell.choose(
+            &format!(
+                "\"{}\" comes from more than one source. Which one should be patched?",
+                sources[0].name
+            ),
+            &options,
+        )? {
+            return Ok(sources[choice]);
+        }
+    }
+
+    Ok(dependencies.remove(0))
+}
+
+/// The range of versions a dependency can use, as `(major, minor, patch)` of the leftmost non-zero part.
+/// Cargo considers versions within the range compatible, and only ever picks one of them.
+///
+/// `None` when the requirement allows any version
+fn compatible_versions(
+    dependency: &metadata::Dependency,
+) -> Option<(u64, Option<u64>, Option<u64>)> {
+    let (major, minor, patch) = match (&dependency.version, &dependency.requirement) {
+        (Some(version), _) => (version.major, Some(version.minor), Some(version.patch)),
+        (None, Some(requirement)) => {
+            let comparator = requirement.comparators.first()?;
+            (comparator.major, comparator.minor, comparator.patch)
+        }
+        (None, None) => return None,
     };
 
-    let project_manifest_toml = toml::patch_manifest(
+    Some(match (major, minor) {
+        (0, Some(0)) => (0, Some(0), patch),
+        (0, minor) => (0, minor, None),
+        (major, _) => (major, None, None),
+    })
+}
+
+/// Checks the version of a patch against every requirement on the crate in the dependency graph,
+/// and checks that it declares every feature its dependents request.
+///
+/// Dependents whose requirement the patch does not meet keep using the original copy of the crate,
+/// leaving the project with two copies of it.
+fn check_dependents(
+    shell: Shell,
+    edges: &[metadata::Edge],
+    dependency: &metadata::Dependency,
+    patch_manifest: &metadata::Crate,
+) -> anyhow::Result<()> {
+    let source = dependency_source(dependency);
+
+    let (compatible, incompatible): (Vec<_>, Vec<_>) = edges
+        .iter()
+        .filter(|edge| edge.name == patch_manifest.name)
+        .filter(|edge| {
+            edge.source
+                .as_deref()
+                .and_then(|edge_source| cargo::core::SourceId::from_url(edge_source).ok())
+                .is_some_and(|edge_source| registry::same_index(edge_source.url().as_str(), source))
+        })
+        .partition(|edge| edge.requirement.matches(&patch_manifest.version));
+
+    let (name, version) = (&patch_manifest.name, &patch_manifest.version);
+
+    if !incompatible.is_empty() {
+        let dependents = incompatible
+            .iter()
+            .map(|edge| format!("{} (requires `{}`)", edge.dependent, edge.requirement))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        ensure!(
+            !compatible.is_empty(),
+            Error::new(
+                Code::VersionIncompatible,
+                format!(
+                    "patch could not be applied because version {version} does not meet the requirement \
+                     of any crate that depends on \"{name}\": {dependents}. Use `--force` to apply it anyway"
+                )
+            )
+        );
+
+        shell.warn(format_args!(
+            "{dependents} will keep using the original \"{name}\", \
+             because version {version} of the patch does not meet their requirements. \
+             The project will have two copies of \"{name}\""
+        ));
+    }
+
+    if let Some(declared) = &patch_manifest.features {
+        let missing = compatible
+            .iter()
+            .flat_map(|edge| &edge.features)
+            // Every crate has a `default` feature, even when it does not declare one.
+            // Features of the crate's own dependencies are checked by cargo
+            .filter(|feature| *feature != "default" && !feature.contains('/'))
+            .filter(|feature| !declared.contains(*feature))
+            .map(|feature| format!("`{feature}`"))
+            .collect::<std::collections::BTreeSet<_>>();
+
+        ensure!(
+            missing.is_empty(),
+            Error::new(
+                Code::FeatureMissing,
+                format!(
+                    "patch could not be applied because it does not declare the feature{} {} \
+                     that the project enables on \"{name}\". Use `--force` to apply it anyway",
+                    if missing.len() == 1 { "" } else { "s" },
+                    missing.into_iter().collect::<Vec<_>>().join(", ")
+                )
+            )
+        );
+    }
+
+    Ok(())
+}
+
+/// Picks the crate to patch with, out of the crates exposed by the patch source.
+///
+/// Sources with several crates, such as workspaces, are narrowed down to the crate the project
+/// depends on. Direct dependencies are checked first, followed by the whole dependency graph.
+///
+/// When several crates remain, the user is asked to choose one if the shell is interactive.
+fn select_crate(
+    shell: Shell,
+    mut candidates: Vec<metadata::Crate>,
+    project_deps: &[metadata::Dependency],
+    edges: &[metadata::Edge],
+    resolved_deps: impl FnOnce() -> anyhow::Result<Vec<metadata::Dependency>>,
+) -> anyhow::Result<metadata::Crate> {
+    let depended_on = |deps: &[metadata::Dependency]| {
+        candidates
+            .iter()
+            .filter(|candidate| deps.iter().any(|dep| dep.name == candidate.name))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    let mut matches = depended_on(project_deps);
+
+    if matches.is_empty() && candidates.len() > 1 {
+        let resolved_deps =
+            resolved_deps().context("failed to get dependencies for current project")?;
+
+        matches = depended_on(&resolved_deps);
+    }
+
+    let names = |crates: &[metadata::Crate]| {
+        crates
+            .iter()
+            .map(|candidate| candidate.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    match matches[..] {
+        // A lone crate may still be a transitive dependency,
+        // which is reported once the dependency is looked up
+        [] if candidates.len() == 1 => Ok(candidates.remove(0)),
+        [] => bail!(Error::new(
+            Code::DependencyNotFound,
+            format!(
+                "none of the packages found ({}) are dependencies of the project. \
+                 Choose one with the `--package` flag",
+                names(&candidates)
+            )
+        )),
+        [_] => Ok(matches.remove(0)),
+        [_, _, ..] => {
+            let options = matches
+                .iter()
+                .map(|candidate| {
+                    let dependents = dependents(edges, &candidate.name);
+                    if dependents.is_empty() {
+                        format!("{} {}", candidate.name, candidate.version)
+                    } else {
+                        format!(
+                            "{} {}, depended on by {dependents}",
+                            candidate.name, candidate.version
+                        )
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            match shell.choose("Which package should be used as the patch?", &options)? {
+                Some(choice) => Ok(matches.remove(choice)),
+                None => bail!(Error::new(
+                    Code::MultiplePackages,
+                    format!(
+                        "multiple candidate packages found ({}). Choose one with the `--package` flag",
+                        names(&matches)
+                    )
+                )),
+            }
+        }
+    }
+}
+
+/// Lists the packages in the dependency graph that depend on the crate `name`
+fn dependents(edges: &[metadata::Edge], name: &str) -> String {
+    let mut dependents = edges
+        .iter()
+        .filter(|edge| edge.name == name)
+        .map(|edge| edge.dependent.as_str())
+        .collect::<Vec<_>>();
+
+    dependents.sort();
+    dependents.dedup();
+
+    dependents.join(", ")
+}
+
+/// Picks every crate exposed by the patch source that the project depends on
+fn select_all_crates(
+    candidates: Vec<metadata::Crate>,
+    project_deps: &[metadata::Dependency],
+    resolved_deps: impl FnOnce() -> anyhow::Result<Vec<metadata::Dependency>>,
+) -> anyhow::Result<Vec<metadata::Crate>> {
+    let resolved_deps =
+        resolved_deps().context("failed to get dependencies for current project")?;
+
+    let (matches, unmatched): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|candidate| {
+        project_deps
+            .iter()
+            .chain(&resolved_deps)
+            .any(|dep| dep.name == candidate.name)
+    });
+
+    ensure!(
+        !matches.is_empty(),
+        Error::new(
+            Code::DependencyNotFound,
+            format!(
+                "none of the packages found ({}) are dependencies of the project",
+                unmatched
+                    .iter()
+                    .map(|candidate| candidate.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        )
+    );
+
+    Ok(matches)
+}
+
+/// Re-resolves dependencies after patches were written, and returns the patches cargo does not use.
+///
+/// Versions of the crates pinned by `Cargo.lock` would stop cargo from picking up the patches,
+/// so those are updated first, like `cargo update -p <crate>` would.
+fn verify_patches<'a>(
+    working_dir: &Path,
+    project_path: &Path,
+    cargo: &context::Cargo,
+    patches: &'a [PendingPatch],
+    lock_content: Option<&str>,
+) -> anyhow::Result<Vec<&'a PendingPatch>> {
+    let mut specs = Vec::new();
+
+    for patch in patches {
+        let name = &patch.krate.name;
+
+        let locked_versions = lock_content
+            .map(|lock| toml::locked_versions(lock, name))
+            .transpose()?
+            .unwrap_or_default();
+
+        specs.extend(
+            locked_versions
+                .iter()
+                .map(|version| format!("{name}@{version}")),
+        );
+    }
+
+    if !specs.is_empty() {
+        metadata::update_lock(project_path, cargo, &specs)?;
+    }
+
+    let packages = metadata::resolved_packages(project_path, cargo)?;
+
+    Ok(patches
+        .iter()
+        .filter(|patch| {
+            !metadata::uses_patch(&packages, &patch.krate.name, &patch.mode, working_dir)
+        })
+        .collect())
+}
+
+/// Reports how the entries of the patched crates in `Cargo.lock` changed from `old_lock`
+fn report_lock_changes(
+    shell: Shell,
+    cargo: &context::Cargo,
+    lock_path: &Path,
+    old_lock: &str,
+    patches: &[PendingPatch],
+) -> anyhow::Result<()> {
+    let new_lock = fs::read_to_string(lock_path).unwrap_or_default();
+
+    for patch in patches {
+        let name = &patch.krate.name;
+
+        let old = toml::locked_packages(old_lock, name)?;
+        let new = toml::locked_packages(&new_lock, name)?;
+
+        let mut removed = old.iter().filter(|package| !new.contains(package));
+        let mut added = new.iter().filter(|package| !old.contains(package));
+
+        loop {
+            let change = match (removed.next(), added.next()) {
+                (Some(from), Some(to)) => LockChange::Updated { from, to },
+                (None, Some(to)) => LockChange::Added { to },
+                (Some(from), None) => LockChange::Removed { from },
+                (None, None) => break,
+            };
+
+            shell.print(&Message::LockUpdated {
+                name,
+                change,
+                offline: cargo.offline,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Clones a git source for `--clone-to`, returning the path patch that points at the clone
+fn clone_source(
+    working_dir: &Path,
+    shell: Shell,
+    url: &url::Url,
+    reference: &cargo::core::GitReference,
+    context::Checkout { dir, branch }: context::Checkout,
+) -> anyhow::Result<context::Mode> {
+    let target = working_dir.join(dir);
+
+    ensure!(
+        !target.exists(),
+        Error::new(
+            Code::GitClone,
+            format!(
+                "unable to clone \"{url}\", because \"{}\" already exists",
+                target.display()
+            )
+        )
+    );
+
+    if let Err(e) = git::clone(url, reference, &target, branch.as_deref()) {
+        // Leave nothing behind when checking out the reference or creating the branch fails
+        let _ = fs::remove_dir_all(&target);
+        return Err(e);
+    }
+
+    shell.print(&Message::SourceCloned {
+        url: url.as_str(),
+        path: &target,
+    });
+
+    Ok(context::Mode::Path(relative_path(working_dir, &target)?))
+}
+
+/// Copies the source of a dependency into a local directory, and then patches it like `--path`
+#[allow(clippy::too_many_arguments)]
+fn edit_dependency(
+    working_dir: &Path,
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    location: Location,
+    shell: Shell,
+    name: &str,
+    version: Option<&semver::Version>,
+    dir: Option<Utf8PathBuf>,
+) -> anyhow::Result<()> {
+    let manifest_path = project_manifest(manifest_dir, cargo)?;
+    let project_path = manifest_path.parent().expect("manifest is a file");
+
+    let packages = metadata::resolved_packages(project_path, cargo)?;
+
+    // Packages without a source are local already, so there is nothing to copy
+    let mut candidates = packages
+        .iter()
+        .filter(|package| package.name == name && package.source.is_some())
+        .filter(|package| version.map_or(true, |version| &package.version == version))
+        .collect::<Vec<_>>();
+
+    candidates.sort_by(|a, b| a.version.cmp(&b.version));
+    candidates.dedup_by(|a, b| a.version == b.version && a.source == b.source);
+
+    let package = match candidates[..] {
+        [] => bail!(Error::new(
+            Code::DependencyNotFound,
+            match version {
+                Some(version) => format!("Unable to find dependency on crate \"{name}\" {version}"),
+                None => format!("Unable to find dependency on crate \"{name}\""),
+            }
+        )),
+        [package] => package,
+        [_, _, ..] => bail!(Error::new(
+            Code::MultiplePackages,
+            format!(
+                "multiple versions of \"{name}\" are used ({}). Choose one with the `--version` flag",
+                candidates
+                    .iter()
+                    .map(|package| package.version.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        )),
+    };
+
+    let target = match dir {
+        Some(dir) => working_dir.join(dir),
+        None => project_path.join("patches").join(name),
+    };
+
+    ensure!(
+        !target.exists(),
+        Error::new(
+            Code::Io,
+            format!(
+                "unable to copy the source of \"{name}\", because \"{}\" already exists",
+                target.display()
+            )
+        )
+    );
+
+    let source = package.manifest_path.parent().expect("manifest is a file");
+
+    copy_dir(source, &target).with_context(|| {
+        Error::new(
+            Code::Io,
+            format!(
+                "failed to copy the source of \"{name}\" to \"{}\"",
+                target.display()
+            ),
+        )
+    })?;
+
+    shell.print(&Message::SourceCopied {
+        name,
+        version: &package.version,
+        path: &target,
+    });
+
+    let relative_target = relative_path(working_dir, &target)?;
+
+    let result = override_dependency(
+        working_dir,
+        manifest_dir,
+        cargo,
+        location,
+        shell,
+        context::Override {
+            packages: context::Packages::Inferred,
+            registry_hint: None,
+            index: None,
+            mode: context::Mode::Path(relative_target),
+            clone_to: None,
+            conflict: context::Conflict::Refuse,
+            force: false,
+            dry_run: false,
+        },
+    );
+
+    // The copy would get in the way of trying again
+    if result.is_err() {
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    result
+}
+
+/// The path of `target`, relative to `working_dir`, as used in `--path`
+fn relative_path(working_dir: &Path, target: &Path) -> anyhow::Result<Utf8PathBuf> {
+    diff_paths(path::absolute(target)?, path::absolute(working_dir)?)
+        .and_then(|target| Utf8PathBuf::try_from(target).ok())
+        .context("path must be utf8 unicode")
+}
+
+/// Recursively copies a crate's source.
+///
+/// Leaves out the files cargo adds when unpacking a crate, and the git metadata of git checkouts.
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+
+        if [".cargo-ok", ".cargo-checksum.json", ".git"].contains(&&*file_name.to_string_lossy()) {
+            continue;
+        }
+
+        let path = entry.path();
+        let target = to.join(&file_name);
+
+        if entry.file_type()?.is_dir() {
+            copy_dir(&path, &target)?;
+        } else {
+            fs::copy(&path, &target)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn remove_override(
+    working_dir: &Path,
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    location: Location,
+    shell: Shell,
+    name: &str,
+    registry: Option<&str>,
+) -> anyhow::Result<()> {
+    let manifest_path = project_manifest(manifest_dir, cargo)?;
+    let patch_path = location.file(&manifest_path, working_dir)?;
+
+    let project_manifest_content = read_patch_file(&patch_path, location)?.unwrap_or_default();
+
+    let (project_manifest_toml, registry) =
+        toml::remove_patch(&project_manifest_content, name, registry)?;
+
+    write_patch_file(&patch_path, &project_manifest_toml)?;
+
+    shell.print(&Message::PatchRemoved {
+        name,
+        registry: &registry,
+        manifest_path: &patch_path,
+    });
+
+    Ok(())
+}
+
+/// Patches every crate of a profile with the profile's source
+fn apply_profile(
+    working_dir: &Path,
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    location: Location,
+    shell: Shell,
+    name: &str,
+) -> anyhow::Result<()> {
+    let manifest_path = project_manifest(manifest_dir, cargo)?;
+    let root = manifest_path.parent().expect("manifest is a file");
+
+    let toml::Profile { source, crates } = toml::profile(
+        &read_patch_file(&manifest_path, Location::Manifest)?.unwrap_or_default(),
+        name,
+    )?;
+
+    let mode = match source {
+        context::Mode::Path(path) => {
+            context::Mode::Path(relative_path(working_dir, &root.join(path))?)
+        }
+        mode => mode,
+    };
+
+    override_dependency(
         working_dir,
-        &project_manifest_content,
-        &project_path,
-        &patch_manifest.name,
-        &registry,
-        &mode,
+        manifest_dir,
+        cargo,
+        location,
+        shell,
+        context::Override {
+            packages: context::Packages::List(crates),
+            registry_hint: None,
+            index: None,
+            mode,
+            clone_to: None,
+            conflict: context::Conflict::Refuse,
+            force: false,
+            dry_run: false,
+        },
+    )
+}
+
+/// Removes the patches of every crate in a profile.
+///
+/// Nothing is written unless all of them are found.
+fn remove_profile(
+    working_dir: &Path,
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    location: Location,
+    shell: Shell,
+    name: &str,
+) -> anyhow::Result<()> {
+    let manifest_path = project_manifest(manifest_dir, cargo)?;
+    let patch_path = location.file(&manifest_path, working_dir)?;
+
+    let toml::Profile { crates, .. } = toml::profile(
+        &read_patch_file(&manifest_path, Location::Manifest)?.unwrap_or_default(),
+        name,
     )?;
 
-    fs::write(&manifest_path, &project_manifest_toml)
-        .context("failed to write patched `Cargo.toml` file")?;
+    let mut content = read_patch_file(&patch_path, location)?.unwrap_or_default();
+    let mut removed = Vec::new();
 
-    eprintln!(
-        "Patched dependency \"{}\" on registry \"{registry}\"",
-        patch_manifest.name
-    );
+    for krate in &crates {
+        let (updated, registry) = toml::remove_patch(&content, krate, None)?;
+
+        content = updated;
+        removed.push((krate, registry));
+    }
+
+    write_patch_file(&patch_path, &content)?;
+
+    for (name, registry) in removed {
+        shell.print(&Message::PatchRemoved {
+            name,
+            registry: &registry,
+            manifest_path: &patch_path,
+        });
+    }
 
     Ok(())
 }
 
-fn project_manifest(manifest_path: &Path, cargo: context::Cargo) -> anyhow::Result<PathBuf> {
+/// Moves a patch in or out of the stash of disabled patches, keeping it intact
+#[allow(clippy::too_many_arguments)]
+fn toggle_override(
+    working_dir: &Path,
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    location: Location,
+    shell: Shell,
+    name: &str,
+    registry: Option<&str>,
+    enable: bool,
+) -> anyhow::Result<()> {
+    let manifest_path = project_manifest(manifest_dir, cargo)?;
+    let patch_path = location.file(&manifest_path, working_dir)?;
+
+    let project_manifest_content = read_patch_file(&patch_path, location)?.unwrap_or_default();
+
+    let config = location != Location::Manifest;
+
+    let (project_manifest_toml, registry) = if enable {
+        toml::enable_patch(&project_manifest_content, name, registry, config)?
+    } else {
+        toml::disable_patch(&project_manifest_content, name, registry, config)?
+    };
+
+    write_patch_file(&patch_path, &project_manifest_toml)?;
+
+    let (registry, manifest_path) = (registry.as_str(), patch_path.as_path());
+
+    shell.print(&if enable {
+        Message::PatchEnabled {
+            name,
+            registry,
+            manifest_path,
+        }
+    } else {
+        Message::PatchDisabled {
+            name,
+            registry,
+            manifest_path,
+        }
+    });
+
+    Ok(())
+}
+
+fn list_overrides(
+    working_dir: &Path,
+    manifest_dir: &Path,
+    cargo: &context::Cargo,
+    location: Location,
+    shell: Shell,
+) -> anyhow::Result<()> {
+    let manifest_path = project_manifest(manifest_dir, cargo)?;
+    let patch_path = location.file(&manifest_path, working_dir)?;
+
+    let project_manifest_content = read_patch_file(&patch_path, location)?.unwrap_or_default();
+
+    let patches = toml::patches(&project_manifest_content)?;
+    let disabled =
+        toml::disabled_patches(&project_manifest_content, location != Location::Manifest)?;
+
+    if patches.is_empty() && disabled.is_empty() {
+        if shell.format() == MessageFormat::Human {
+            eprintln!("No patches found in \"{}\"", patch_path.display());
+        }
+        return Ok(());
+    }
+
+    let project_path = manifest_path.parent().expect("manifest is a file");
+    let root = location.root(&patch_path);
+
+    // Listing is not a change, so the `Cargo.lock` that resolving updates is put back
+    let lock_restore = LockRestore::new(project_path.join(CARGO_LOCK));
+
+    let packages = match metadata::resolved_packages(project_path, cargo) {
+        Ok(packages) => Some(packages),
+        Err(e) => {
+            shell.warn(format_args!(
+                "unable to check which patches are used: {e:?}"
+            ));
+            None
+        }
+    };
+
+    drop(lock_restore);
+
+    let status = |patch: &toml::Patch| match (&patch.source, &packages) {
+        (Some(source), Some(packages))
+            if metadata::uses_patch(packages, &patch.name, source, root) =>
+        {
+            "used"
+        }
+        (Some(_), Some(_)) => "unused",
+        _ => "unknown",
+    };
+
+    let patches = patches
+        .iter()
+        .map(|patch| (patch, status(patch)))
+        .chain(disabled.iter().map(|patch| (patch, "disabled")));
+
+    if shell.format() == MessageFormat::Json {
+        for (patch, status) in patches {
+            shell.print(&Message::Patch {
+                name: &patch.name,
+                registry: &patch.registry,
+                source: patch
+                    .source
+                    .as_ref()
+                    .map(|source| toml::source_fields(root, Some(root), source)),
+                status,
+                manifest_path: &patch_path,
+            });
+        }
+
+        return Ok(());
+    }
+
+    let rows = patches
+        .map(|(patch, status)| {
+            let source = patch
+                .source
+                .as_ref()
+                .map_or_else(|| "unknown source".to_owned(), ToString::to_string);
+
+            [
+                patch.name.clone(),
+                patch.registry.clone(),
+                source,
+                status.to_owned(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let width = |column: usize| rows.iter().map(|row| row[column].len()).max();
+    let [name_width, registry_width, source_width] =
+        [0, 1, 2].map(|column| width(column).unwrap_or(0));
+
+    for [name, registry, source, status] in rows {
+        println!(
+            "{name:<name_width$}  {registry:<registry_width$}  {source:<source_width$}  {status}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Reads the file patches are stored in.
+///
+/// Returns `None` for config files that do not exist yet.
+fn read_patch_file(path: &Path, location: Location) -> anyhow::Result<Option<String>> {
+    if location != Location::Manifest && !path.exists() {
+        return Ok(None);
+    }
+
+    fs::read_to_string(path)
+        .map(Some)
+        .with_context(|| Error::new(Code::Io, format!("failed to read \"{}\"", path.display())))
+}
+
+fn write_patch_file(path: &Path, content: &str) -> anyhow::Result<()> {
+    let write = || {
+        fs::create_dir_all(path.parent().expect("patch location is a file"))?;
+        fs::write(path, content)
+    };
+
+    write().with_context(|| Error::new(Code::Io, format!("failed to write \"{}\"", path.display())))
+}
+
+fn project_manifest(manifest_path: &Path, cargo: &context::Cargo) -> anyhow::Result<PathBuf> {
     let manifest = metadata::workspace_root(manifest_path, cargo)?.join(CARGO_TOML);
 
     debug_assert!(manifest.is_file(), "{:?} is not a file", manifest);
@@ -0,0 +1,67 @@
+use crate::error::{Code, Error};
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use home::cargo_home_with_cwd;
+
+/// The file `[patch]` entries are written to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Location {
+    #[default]
+    Manifest,
+    Config,
+    User,
+}
+
+impl Location {
+    /// Finds the file patches are stored in, given the workspace root `Cargo.toml`.
+    ///
+    /// Config files may not exist yet, in which case they are created when patches are written.
+    pub fn file(self, workspace_manifest: &Path, working_dir: &Path) -> anyhow::Result<PathBuf> {
+        let config_dir = match self {
+            Location::Manifest => return Ok(workspace_manifest.to_path_buf()),
+            Location::Config => workspace_manifest
+                .parent()
+                .expect("manifest is a file")
+                .join(".cargo"),
+            Location::User => cargo_home_with_cwd(working_dir).context(Error::new(
+                Code::CargoConfig,
+                "failed to find the cargo home directory",
+            ))?,
+        };
+
+        // Cargo still reads `config` when `config.toml` does not exist
+        let legacy_config = config_dir.join("config");
+        let config = config_dir.join("config.toml");
+
+        Ok(if !config.exists() && legacy_config.is_file() {
+            legacy_config
+        } else {
+            config
+        })
+    }
+
+    /// The directory that relative paths in `file` are resolved from.
+    ///
+    /// Cargo resolves paths in config files from the parent of the directory holding the config,
+    /// so a `[patch]` in `<workspace>/.cargo/config.toml` is relative to `<workspace>`.
+    pub fn root(self, file: &Path) -> &Path {
+        let directory = file.parent().expect("patch location is a file");
+
+        match self {
+            Location::Manifest => directory,
+            Location::Config | Location::User => directory.parent().unwrap_or(directory),
+        }
+    }
+
+    /// The directory new path patches are written relative to.
+    ///
+    /// Patches in the user's config apply to every project, so absolute paths are used there.
+    pub fn relative_to(self, file: &Path) -> Option<&Path> {
+        match self {
+            Location::Manifest | Location::Config => Some(self.root(file)),
+            Location::User => None,
+        }
+    }
+}
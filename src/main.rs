@@ -7,8 +7,10 @@ use clap::Parser;
 fn main() {
     let args = Cli::parse();
 
-    if let Err(e) = run(&current_dir().unwrap(), args) {
-        eprintln!("error: {e:?}");
+    let shell = args.shell();
+
+    if let Err(e) = run(&current_dir().unwrap(), args, shell) {
+        shell.error(&e);
         process::exit(101)
     }
 }
@@ -1,46 +1,134 @@
-use crate::context;
+use crate::{
+    context,
+    error::{Code, Error},
+};
 
-use std::{ops::Not, path::PathBuf};
+use std::{
+    collections::BTreeSet,
+    env,
+    ops::Not,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use anyhow::{bail, Context as _};
-use cargo::core::PackageIdSpec;
+use cargo::core::{PackageIdSpec, SourceId};
+use cargo_util_schemas::core::SourceKind;
+use fs_err as fs;
 use semver::{Version, VersionReq};
+use serde::Serialize;
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub struct Crate {
     pub name: String,
     pub version: Version,
+    /// The directory holding the crate's `Cargo.toml`
+    #[serde(skip)]
+    pub root: PathBuf,
+    /// The features the crate declares, including those of optional dependencies.
+    /// `None` when they are not known, as for versions from a registry
+    #[serde(skip)]
+    pub features: Option<BTreeSet<String>>,
 }
 
+/// Finds the crates a patch source exposes.
+///
+/// Unless packages are named, every member of the source's workspace is returned,
+/// not only its default members, as the project may depend on any of them.
 pub fn crate_details(
     project_dir: impl Into<PathBuf>,
-    cargo: context::Cargo,
-) -> Result<Crate, anyhow::Error> {
+    cargo: &context::Cargo,
+    packages: &context::Packages,
+) -> Result<Vec<Crate>, anyhow::Error> {
     let project_dir = project_dir.into();
 
     let metadata = cargo_metadata(&project_dir, cargo, false)?;
 
-    let root_packages = metadata.workspace_default_packages();
+    let named = packages.named();
 
-    let package = match root_packages[..] {
-        [] => {
-            bail!("no package found in directory \"{project_dir:?}\"")
-        }
-        [_, _, ..] => {
-            bail!("multiple candidate packages found in directory \"{project_dir:?}\"")
-        }
-        [package] => package,
+    let packages = match named {
+        Some(names) => metadata
+            .workspace_packages()
+            .into_iter()
+            .filter(|candidate| names.contains(&candidate.name))
+            .collect(),
+        None => metadata.workspace_packages(),
     };
 
-    Ok(Crate {
-        name: package.name.clone(),
-        version: package.version.clone(),
-    })
+    let missing = named.and_then(|names| {
+        names
+            .iter()
+            .find(|name| packages.iter().all(|package| &package.name != *name))
+    });
+
+    if let Some(package) = missing {
+        bail!(Error::new(
+            Code::PackageNotFound,
+            format!("no package named \"{package}\" found in directory \"{project_dir:?}\"")
+        ))
+    }
+
+    if packages.is_empty() {
+        bail!(Error::new(
+            Code::PackageNotFound,
+            format!("no package found in directory \"{project_dir:?}\"")
+        ))
+    }
+
+    Ok(packages
+        .into_iter()
+        .map(|package| Crate {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            root: package
+                .manifest_path
+                .parent()
+                .expect("manifest is a file")
+                .into(),
+            features: Some(declared_features(package)),
+        })
+        .collect())
+}
+
+/// Explicit features, along with the implicit features of optional dependencies
+/// that are not only referred to with `dep:`
+fn declared_features(package: &cargo_metadata::Package) -> BTreeSet<String> {
+    let mut features = package.features.keys().cloned().collect::<BTreeSet<_>>();
+
+    let dep_prefixed = |name: &str| {
+        package
+            .features
+            .values()
+            .flatten()
+            .any(|value| value.strip_prefix("dep:") == Some(name))
+    };
+
+    features.extend(
+        package
+            .dependencies
+            .iter()
+            .filter(|dependency| dependency.optional)
+            .map(|dependency| dependency.rename.as_ref().unwrap_or(&dependency.name))
+            .filter(|name| !dep_prefixed(name))
+            .cloned(),
+    );
+
+    features
+}
+
+/// The directory cargo writes build output to, where `cargo-override` keeps its journal
+pub fn target_directory(
+    project_dir: impl Into<PathBuf>,
+    cargo: &context::Cargo,
+) -> Result<PathBuf, anyhow::Error> {
+    let metadata = cargo_metadata(project_dir, cargo, false)?;
+
+    Ok(metadata.target_directory.into())
 }
 
 pub fn workspace_root(
     project_dir: impl Into<PathBuf>,
-    cargo: context::Cargo,
+    cargo: &context::Cargo,
 ) -> Result<PathBuf, anyhow::Error> {
     let metadata = cargo_metadata(project_dir, cargo, false)?;
 
@@ -51,42 +139,65 @@ pub fn workspace_root(
 pub struct Dependency {
     pub name: String,
     pub requirement: Option<VersionReq>,
+    /// The version the dependency resolved to. `None` for dependencies that are not resolved
+    pub version: Option<Version>,
     pub registry: Option<String>,
+    /// URL of the repository, for dependencies sourced from git
+    pub git: Option<String>,
+    /// The manifest declaring the dependency. `None` for dependencies that are not direct
+    pub manifest_path: Option<PathBuf>,
+    /// Whether the dependency comes from a local path, as it does once it is patched with one
+    pub path: bool,
 }
 
 pub fn direct_dependencies(
     project_dir: impl Into<PathBuf>,
-    cargo: context::Cargo,
+    cargo: &context::Cargo,
 ) -> Result<Vec<Dependency>, anyhow::Error> {
     let metadata = cargo_metadata(project_dir, cargo, false)?;
 
     Ok(metadata
         .packages
         .into_iter()
-        .flat_map(|package| package.dependencies)
-        .map(
-            |cargo_metadata::Dependency {
-                 name,
-                 req,
-                 registry,
-                 ..
-             }| Dependency {
-                name: name.clone(),
-                requirement: Some(req.clone()),
-                registry: registry.clone(),
-            },
-        )
+        .flat_map(|package| {
+            let manifest_path = PathBuf::from(package.manifest_path);
+
+            package.dependencies.into_iter().map(
+                move |cargo_metadata::Dependency {
+                          name,
+                          req,
+                          registry,
+                          source,
+                          path,
+                          ..
+                      }| Dependency {
+                    name: name.clone(),
+                    requirement: Some(req.clone()),
+                    version: None,
+                    registry: registry.clone(),
+                    git: source
+                        .and_then(|source| SourceId::from_url(&source).ok())
+                        .filter(|source| source.is_git())
+                        .map(|source| source.url().to_string()),
+                    manifest_path: Some(manifest_path.clone()),
+                    path: path.is_some(),
+                },
+            )
+        })
         .collect())
 }
 
 pub fn resolved_dependencies(
     project_dir: impl Into<PathBuf>,
-    cargo: context::Cargo,
+    cargo: &context::Cargo,
 ) -> Result<Vec<Dependency>, anyhow::Error> {
     let metadata = cargo_metadata(project_dir, cargo, true)?;
 
     let Some(cargo_metadata::Resolve { nodes, .. }) = metadata.resolve else {
-        bail!("failed to resolve transative dependencies")
+        bail!(Error::new(
+            Code::CargoMetadata,
+            "failed to resolve transitive dependencies"
+        ))
     };
 
     Ok(nodes
@@ -94,26 +205,240 @@ pub fn resolved_dependencies(
         .map(|node| PackageIdSpec::parse(&node.id.repr))
         .flat_map(|package| package.ok())
         .flat_map(|package| {
+            let git = match package.kind() {
+                Some(SourceKind::Git(_)) => package.url().map(ToString::to_string),
+                _ => None,
+            };
+
             Some(Dependency {
                 name: package.name().to_owned(),
                 registry: Some(package.url()?.to_string()),
                 requirement: None,
+                version: package.version(),
+                git,
+                manifest_path: None,
+                path: matches!(package.kind(), Some(SourceKind::Path)),
             })
         })
         .collect())
 }
 
+/// A dependency of one package on another, in the resolved dependency graph
+#[derive(Clone)]
+pub struct Edge {
+    /// Name of the package depended on
+    pub name: String,
+    pub requirement: VersionReq,
+    /// Source of the package the dependency resolved to. `None` for path dependencies
+    pub source: Option<String>,
+    /// Name and version of the package declaring the dependency
+    pub dependent: String,
+    /// Features the dependent requests on the package depended on, including `default`
+    /// unless default features are turned off
+    pub features: Vec<String>,
+}
+
+/// Collects every dependency in the resolved dependency graph, along with its version requirement
+pub fn dependency_edges(
+    project_dir: impl Into<PathBuf>,
+    cargo: &context::Cargo,
+) -> Result<Vec<Edge>, anyhow::Error> {
+    let metadata = cargo_metadata(project_dir, cargo, true)?;
+
+    let Some(cargo_metadata::Resolve { nodes, .. }) = &metadata.resolve else {
+        bail!(Error::new(
+            Code::CargoMetadata,
+            "failed to resolve transitive dependencies"
+        ))
+    };
+
+    let mut edges = Vec::new();
+
+    for node in nodes {
+        let package = &metadata[&node.id];
+
+        for node_dep in &node.deps {
+            let target = &metadata[&node_dep.pkg];
+
+            // A package can depend on several versions of a crate under different names,
+            // so the declaration is found by the name the dependency resolved under
+            let requirements = package.dependencies.iter().filter(|dependency| {
+                dependency.name == target.name
+                    && dependency
+                        .rename
+                        .as_ref()
+                        .map_or(true, |rename| rename.replace('-', "_") == node_dep.name)
+                    && (dependency.req == VersionReq::STAR
+                        || dependency.req.matches(&target.version))
+                    && node_dep
+                        .dep_kinds
+                        .iter()
+                        .any(|kind| kind.kind == dependency.kind)
+            });
+
+            for dependency in requirements {
+                edges.push(Edge {
+                    name: target.name.clone(),
+                    requirement: dependency.req.clone(),
+                    source: target.source.as_ref().map(|source| source.repr.clone()),
+                    dependent: format!("{} {}", package.name, package.version),
+                    features: requested_features(dependency),
+                });
+            }
+        }
+    }
+
+    edges.dedup_by(|a, b| {
+        a.name == b.name && a.requirement == b.requirement && a.dependent == b.dependent
+    });
+
+    Ok(edges)
+}
+
+/// The features a dependency declaration asks for, as written by the dependent
+fn requested_features(dependency: &cargo_metadata::Dependency) -> Vec<String> {
+    let mut features = dependency.features.clone();
+
+    if dependency.uses_default_features {
+        features.push("default".to_owned());
+    }
+
+    features
+}
+
+/// A package selected by cargo when resolving the dependency graph
+#[derive(Clone)]
+pub struct Package {
+    pub name: String,
+    pub version: Version,
+    /// Source of the package. `None` for path dependencies
+    pub source: Option<String>,
+    pub manifest_path: PathBuf,
+}
+
+pub fn resolved_packages(
+    project_dir: impl Into<PathBuf>,
+    cargo: &context::Cargo,
+) -> Result<Vec<Package>, anyhow::Error> {
+    let metadata = cargo_metadata(project_dir, cargo, true)?;
+
+    Ok(metadata
+        .packages
+        .into_iter()
+        .map(|package| Package {
+            name: package.name,
+            version: package.version,
+            source: package.source.map(|source| source.repr),
+            manifest_path: package.manifest_path.into(),
+        })
+        .collect())
+}
+
+/// Checks if cargo resolved the crate `name` to the given patch source.
+///
+/// Relative patch paths are resolved from `base_dir`.
+pub fn uses_patch(
+    packages: &[Package],
+    name: &str,
+    source: &context::Mode,
+    base_dir: &Path,
+) -> bool {
+    let mut packages = packages.iter().filter(|package| package.name == name);
+
+    match source {
+        context::Mode::Path(path) => {
+            let Ok(patch_dir) = fs::canonicalize(base_dir.join(path)) else {
+                return false;
+            };
+
+            packages.any(|package| {
+                package.source.is_none()
+                    && package
+                        .manifest_path
+                        .parent()
+                        .and_then(|dir| fs::canonicalize(dir).ok())
+                        .is_some_and(|dir| dir == patch_dir)
+            })
+        }
+        context::Mode::Git { url, reference } => {
+            let Ok(patch_source) = SourceId::for_git(url, reference.clone()) else {
+                return false;
+            };
+
+            packages.any(|package| {
+                package
+                    .source
+                    .as_deref()
+                    .and_then(|source| SourceId::from_url(source).ok())
+                    .is_some_and(|source| source == patch_source)
+            })
+        }
+        context::Mode::Registry { requirement } => packages.any(|package| {
+            package.source.as_deref() == Some(crate::CRATES_IO_SPARSE_INDEX)
+                && requirement.matches(&package.version)
+        }),
+    }
+}
+
+/// Runs `cargo update` for the given package ID specifications, so that `Cargo.lock` can pick up new patches
+pub fn update_lock(
+    project_dir: impl Into<PathBuf>,
+    cargo: &context::Cargo,
+    specs: &[String],
+) -> anyhow::Result<()> {
+    let project_dir = project_dir.into();
+
+    let output = Command::new(env::var_os("CARGO").unwrap_or_else(|| "cargo".into()))
+        .envs(cargo.registry_env(&project_dir)?)
+        .current_dir(project_dir)
+        .arg("update")
+        .args(specs.iter().flat_map(|spec| ["-p", spec]))
+        .args(
+            [
+                cargo.locked.then_some("--locked"),
+                cargo.offline.then_some("--offline"),
+                Some("--color"),
+                Some("never"),
+            ]
+            .into_iter()
+            .flatten(),
+        )
+        .args(cargo.config_args())
+        .output()
+        .context(Error::new(
+            Code::CargoUpdate,
+            "failed to start `cargo update`",
+        ))?;
+
+    if !output.status.success() {
+        bail!(Error::new(
+            Code::CargoUpdate,
+            format!(
+                "`cargo update` exited with an error: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )
+        ))
+    }
+
+    Ok(())
+}
+
 fn cargo_metadata(
     project_dir: impl Into<PathBuf>,
-    context::Cargo { locked, offline }: context::Cargo,
+    cargo: &context::Cargo,
     include_deps: bool,
 ) -> anyhow::Result<cargo_metadata::Metadata> {
+    let project_dir = project_dir.into();
+
     let mut cmd = cargo_metadata::MetadataCommand::new();
+    for (key, value) in cargo.registry_env(&project_dir)? {
+        cmd.env(key, value);
+    }
     cmd.current_dir(project_dir);
     cmd.other_options(
         [
-            locked.then_some("--locked"),
-            offline.then_some("--offline"),
+            cargo.locked.then_some("--locked"),
+            cargo.offline.then_some("--offline"),
             include_deps.not().then_some("--no-deps"),
             Some("--color"),
             Some("never"),
@@ -121,7 +446,11 @@ fn cargo_metadata(
         .into_iter()
         .flatten()
         .map(str::to_owned)
+        .chain(cargo.config_args())
         .collect::<Vec<_>>(),
     );
-    cmd.exec().context("Unable to run `cargo metadata`")
+    cmd.exec().context(Error::new(
+        Code::CargoMetadata,
+        "Unable to run `cargo metadata`",
+    ))
 }
@@ -1,29 +1,185 @@
-use std::{collections::HashMap, env, ffi::OsString, io, path::PathBuf};
+use crate::{
+    context,
+    error::{Code, Error},
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    task::Poll,
+};
 
 use anyhow::Context;
-use cargo::{core::shell::Shell, util::context::GlobalContext};
+use cargo::{
+    core::{Dependency, SourceId},
+    sources::{
+        source::{QueryKind, Source as _},
+        IndexSummary, RegistrySource,
+    },
+    util::{
+        cache_lock::CacheLockMode,
+        context::{ConfigRelativePath, GlobalContext},
+        CanonicalUrl,
+    },
+};
+use fs_err as fs;
+use semver::{Version, VersionReq};
+use url::Url;
 use winnow::{token::take_until, PResult, Parser};
 
+/// Checks if two registry index URLs point at the same registry.
+///
+/// `registry+` prefixes, trailing slashes and `.git` suffixes are ignored, so that differently spelled
+/// URLs of one index match. Cargo itself only ignores them for git sources, and compares registries by exact URL.
+/// Like in cargo, the sparse index of crates.io is a different source from its git index.
+pub fn same_index(a: &str, b: &str) -> bool {
+    match (canonical_index(a), canonical_index(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Checks if `url` is the index of crates.io that `[patch.crates-io]` patches
+pub fn is_crates_io(url: &str) -> bool {
+    same_index(url, crate::DEFAULT_REGISTRY_URL)
+}
+
+/// The `sparse+` prefix is kept as part of the URL scheme, so sparse and git indexes never compare equal
+fn canonical_index(url: &str) -> Option<CanonicalUrl> {
+    let url = url.strip_prefix("registry+").unwrap_or(url);
+
+    CanonicalUrl::new(&Url::parse(url).ok()?).ok()
+}
+
+/// Finds the newest version of the crate `name` on crates.io that meets `requirement`.
+///
+/// Yanked versions are left out, as cargo does not use them for patches.
+pub fn newest_version(
+    working_dir: &Path,
+    cargo: &context::Cargo,
+    name: &str,
+    requirement: &VersionReq,
+) -> anyhow::Result<Version> {
+    let global_context = cargo.global_context(working_dir)?;
+
+    let source_id = SourceId::from_url(crate::CRATES_IO_SPARSE_INDEX)?;
+
+    let query = || -> anyhow::Result<Vec<IndexSummary>> {
+        let _package_lock =
+            global_context.acquire_package_cache_lock(CacheLockMode::DownloadExclusive)?;
+
+        let mut source = RegistrySource::remote(source_id, &HashSet::new(), &global_context)?;
+        source.invalidate_cache();
+
+        let dependency = Dependency::parse(name, Some(&requirement.to_string()), source_id)?;
+
+        loop {
+            match source.query_vec(&dependency, QueryKind::Exact)? {
+                Poll::Ready(summaries) => return Ok(summaries),
+                Poll::Pending => source.block_until_ready()?,
+            }
+        }
+    };
+
+    let summaries = query().with_context(|| {
+        Error::new(
+            Code::RegistryQuery,
+            format!("failed to look up the versions of \"{name}\" on crates.io"),
+        )
+    })?;
+
+    summaries
+        .iter()
+        .filter_map(|summary| match summary {
+            IndexSummary::Candidate(summary) => Some(summary.version()),
+            _ => None,
+        })
+        .max()
+        .cloned()
+        .with_context(|| {
+            Error::new(
+                Code::PackageNotFound,
+                format!(
+                    "no version of \"{name}\" on crates.io meets the requirement `{requirement}`"
+                ),
+            )
+        })
+}
+
+/// Finds the name of the registry with the index `registry_url`,
+/// from the cargo config loaded for `working_dir`.
+///
+/// When `resolved` is set, `registry_url` is the source a dependency resolved to,
+/// which may stand in for another source through source replacement.
+/// Registries that dependencies declare themselves are never replacements.
 pub fn get_registry_name_from_url(
-    working_dir: PathBuf,
+    working_dir: &Path,
+    cargo: &context::Cargo,
+    registry_url: &str,
+    resolved: bool,
+) -> anyhow::Result<Option<String>> {
+    let global_context = cargo.global_context(working_dir)?;
+
+    if !resolved {
+        return registry_name(&global_context, registry_url);
+    }
+
+    let sources: Option<HashMap<String, SourceConfig>> =
+        global_context.get("source").context(Error::new(
+            Code::CargoConfig,
+            "failed to fetch sources from cargo global context",
+        ))?;
+
+    let sources = sources
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(name, source)| {
+            let source = Source {
+                registry: source.registry,
+                path: source
+                    .directory
+                    .or(source.local_registry)
+                    .map(|path| path.resolve_path(&global_context)),
+                replace_with: source.replace_with,
+            };
+
+            (name, source)
+        })
+        .collect();
+
+    // Patches are keyed on the source a mirror or vendored source stands in for
+    let Some(original) = replaced_source(&sources, registry_url) else {
+        return registry_name(&global_context, registry_url);
+    };
+
+    match original.registry {
+        _ if original.name == crate::DEFAULT_REGISTRY => Ok(Some(original.name)),
+        Some(url) => Ok(registry_name(&global_context, &url)?.or(Some(original.name))),
+        None => Ok(Some(original.name)),
+    }
+}
+
+/// Finds the name of the registry with the index `registry_url`,
+/// from environment variables, the `[env]` config and the `[registries]` config
+fn registry_name(
+    global_context: &GlobalContext,
     registry_url: &str,
 ) -> anyhow::Result<Option<String>> {
     if let Some(registry) = get_registry_from_env(env::vars_os(), registry_url) {
         return Ok(Some(registry));
     }
 
-    let shell = Shell::from_write(Box::new(io::sink()));
-
-    let global_context = GlobalContext::new(shell, working_dir.clone(), working_dir);
     let config_env = global_context
         .env_config()
-        .context("failed to get [env] config")?;
+        .context(Error::new(Code::CargoConfig, "failed to get [env] config"))?;
 
     if let Some(registry) = get_registry_from_env(
         config_env.iter().map(|(key, value)| {
             (
                 OsString::from(key),
-                value.resolve(&global_context).into_owned(),
+                value.resolve(global_context).into_owned(),
             )
         }),
         registry_url,
@@ -36,14 +192,16 @@ pub fn get_registry_name_from_url(
         index: String,
     }
 
-    let cargo_config_map: Option<HashMap<String, Registry>> = global_context
-        .get("registries")
-        .context("failed to fetch registries from cargo global context")?;
+    let cargo_config_map: Option<HashMap<String, Registry>> =
+        global_context.get("registries").context(Error::new(
+            Code::CargoConfig,
+            "failed to fetch registries from cargo global context",
+        ))?;
 
     if let Some((key, _)) = cargo_config_map
         .into_iter()
         .flatten()
-        .find(|(_, Registry { index })| index == registry_url)
+        .find(|(_, Registry { index })| same_index(index, registry_url))
     {
         return Ok(Some(key));
     }
@@ -51,6 +209,87 @@ pub fn get_registry_name_from_url(
     Ok(None)
 }
 
+/// A `[source]` table of the cargo config
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct SourceConfig {
+    registry: Option<String>,
+    directory: Option<ConfigRelativePath>,
+    local_registry: Option<ConfigRelativePath>,
+    replace_with: Option<String>,
+}
+
+/// A `[source]` table, with the path of `directory` and `local-registry` sources resolved
+#[derive(Clone, Default)]
+struct Source {
+    registry: Option<String>,
+    path: Option<PathBuf>,
+    replace_with: Option<String>,
+}
+
+impl Source {
+    /// Checks if the source is the one with the index or location `url`
+    fn is(&self, url: &str) -> bool {
+        let registry = self
+            .registry
+            .as_deref()
+            .is_some_and(|registry| same_index(registry, url));
+
+        registry
+            || self
+                .path
+                .as_deref()
+                .is_some_and(|path| same_path(path, url))
+    }
+}
+
+/// Checks if a `file://` URL, optionally prefixed with the kind of source, points at `path`
+fn same_path(path: &Path, url: &str) -> bool {
+    let url = url.split_once('+').map_or(url, |(_, url)| url);
+
+    let Some(url_path) = Url::parse(url).ok().and_then(|url| url.to_file_path().ok()) else {
+        return false;
+    };
+
+    match (fs::canonicalize(path), fs::canonicalize(&url_path)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => path == url_path,
+    }
+}
+
+/// The source that is replaced by another source
+#[derive(Debug, PartialEq)]
+struct Replaced {
+    name: String,
+    registry: Option<String>,
+}
+
+/// Follows `[source]` replacement chains back from the source with the index or location `url`,
+/// to the source that the chain replaces. `None` when nothing replaces the source
+fn replaced_source(sources: &HashMap<String, Source>, url: &str) -> Option<Replaced> {
+    let (start, _) = sources.iter().find(|(_, source)| source.is(url))?;
+
+    let mut name = start;
+    let mut visited = HashSet::from([start]);
+
+    loop {
+        let mut replacing = sources
+            .iter()
+            .filter(|(_, source)| source.replace_with.as_ref() == Some(name));
+
+        match (replacing.next(), replacing.next()) {
+            (Some((replaced, _)), None) if visited.insert(replaced) => name = replaced,
+            // Stop at the start of the chain, and when it is ambiguous or loops
+            _ => break,
+        }
+    }
+
+    (name != start).then(|| Replaced {
+        name: name.clone(),
+        registry: sources[name].registry.clone(),
+    })
+}
+
 fn get_registry_from_env(
     env: impl Iterator<Item = (OsString, OsString)>,
     url: &str,
@@ -67,7 +306,7 @@ fn get_registry_from_env(
             // TODO: we should probably throw a warning here
             continue;
         };
-        if registry_url == url {
+        if same_index(&registry_url, url) {
             return Some(registry.replace('_', "-").to_lowercase());
         }
     }
@@ -84,12 +323,14 @@ fn registry_key(input: &str) -> PResult<&str> {
 mod tests {
     use super::*;
 
-    use std::ffi::OsString;
+    use std::{ffi::OsString, io};
 
+    use cargo::core::Shell;
     use googletest::{
         expect_that,
         matchers::{eq, none, some},
     };
+    use tempfile::TempDir;
 
     #[googletest::test]
     fn find_registry_from_url() {
@@ -128,4 +369,151 @@ mod tests {
             none()
         );
     }
+
+    #[googletest::test]
+    fn compare_index_urls() {
+        for (a, b) in [
+            (
+                "registry+https://github.com/rust-lang/crates.io-index",
+                "https://github.com/rust-lang/crates.io-index.git",
+            ),
+            (
+                "sparse+https://dl.cloudsmith.io/basic/private/registry/cargo/",
+                "sparse+https://dl.cloudsmith.io/basic/private/registry/cargo",
+            ),
+            (
+                "https://dl.cloudsmith.io/basic/private/registry/cargo/index.git",
+                "registry+https://dl.cloudsmith.io/basic/private/registry/cargo/index",
+            ),
+        ] {
+            expect_that!(same_index(a, b), eq(true));
+        }
+
+        for (a, b) in [
+            (
+                "https://github.com/rust-lang/crates.io-index",
+                "sparse+https://index.crates.io/",
+            ),
+            (
+                "sparse+https://dl.cloudsmith.io/basic/private/registry/cargo/",
+                "https://dl.cloudsmith.io/basic/private/registry/cargo/",
+            ),
+            (
+                "https://github.com/rust-lang/crates.io-index",
+                "https://dl.cloudsmith.io/basic/private/registry/cargo/index.git",
+            ),
+        ] {
+            expect_that!(same_index(a, b), eq(false));
+        }
+    }
+
+    #[googletest::test]
+    fn crates_io_indexes_match_cargo() {
+        let cargo_home = TempDir::new().unwrap();
+
+        // Without the user's configuration and environment, which could replace the crates.io index
+        let mut global_context = GlobalContext::new(
+            Shell::from_write(Box::new(io::sink())),
+            cargo_home.path().to_path_buf(),
+            cargo_home.path().to_path_buf(),
+        );
+        global_context.set_env(HashMap::new());
+
+        let git = SourceId::crates_io(&global_context).unwrap();
+        let sparse = SourceId::crates_io_maybe_sparse_http(&global_context).unwrap();
+
+        for (spelling, source) in [
+            (crate::DEFAULT_REGISTRY_URL, git),
+            (crate::CRATES_IO_SPARSE_INDEX, sparse),
+        ] {
+            expect_that!(same_index(spelling, &source.as_url().to_string()), eq(true));
+            expect_that!(is_crates_io(spelling), eq(source == git));
+        }
+
+        expect_that!(
+            same_index(crate::DEFAULT_REGISTRY_URL, crate::CRATES_IO_SPARSE_INDEX),
+            eq(git == sparse)
+        );
+    }
+
+    #[googletest::test]
+    fn follow_source_replacement() {
+        let vendor_dir = env::temp_dir().join("vendor");
+
+        let sources = HashMap::from([
+            (
+                "crates-io".to_owned(),
+                Source {
+                    replace_with: Some("mirror".to_owned()),
+                    ..Source::default()
+                },
+            ),
+            (
+                "mirror".to_owned(),
+                Source {
+                    registry: Some("sparse+https://mirror.example.com/index/".to_owned()),
+                    replace_with: Some("vendored-sources".to_owned()),
+                    ..Source::default()
+                },
+            ),
+            (
+                "vendored-sources".to_owned(),
+                Source {
+                    path: Some(vendor_dir.clone()),
+                    ..Source::default()
+                },
+            ),
+            (
+                "private".to_owned(),
+                Source {
+                    registry: Some("https://private.example.com/index".to_owned()),
+                    replace_with: Some("private-mirror".to_owned()),
+                    ..Source::default()
+                },
+            ),
+            (
+                "private-mirror".to_owned(),
+                Source {
+                    registry: Some("https://private-mirror.example.com/index".to_owned()),
+                    ..Source::default()
+                },
+            ),
+        ]);
+
+        expect_that!(
+            replaced_source(&sources, "sparse+https://mirror.example.com/index"),
+            some(eq(&Replaced {
+                name: "crates-io".to_owned(),
+                registry: None,
+            }))
+        );
+        expect_that!(
+            replaced_source(
+                &sources,
+                &format!(
+                    "directory+{}",
+                    Url::from_directory_path(&vendor_dir).unwrap()
+                )
+            ),
+            some(eq(&Replaced {
+                name: "crates-io".to_owned(),
+                registry: None,
+            }))
+        );
+        expect_that!(
+            replaced_source(&sources, "https://private-mirror.example.com/index"),
+            some(eq(&Replaced {
+                name: "private".to_owned(),
+                registry: Some("https://private.example.com/index".to_owned()),
+            }))
+        );
+        expect_that!(
+            replaced_source(&sources, "https://private.example.com/index"),
+            none()
+        );
+        expect_that!(
+            replaced_source(&sources, "https://unknown.example.com/index"),
+            none()
+        );
+    }
 }
@@ -0,0 +1,332 @@
+use crate::error;
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, BufRead as _, IsTerminal as _, Write as _},
+    path::Path,
+};
+
+use anyhow::Context as _;
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MessageFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Reports progress, warnings and errors in the format chosen with `--message-format`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Shell {
+    format: MessageFormat,
+}
+
+/// A message that can be printed for humans, or serialized for machines
+#[derive(Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum Message<'a> {
+    PatchApplied {
+        #[serde(rename = "crate")]
+        krate: &'a crate::metadata::Crate,
+        registry: &'a str,
+        source: BTreeMap<String, String>,
+        manifest_path: &'a Path,
+    },
+    PatchPreview {
+        #[serde(rename = "crate")]
+        krate: &'a crate::metadata::Crate,
+        registry: &'a str,
+        source: BTreeMap<String, String>,
+        manifest_path: &'a Path,
+        diff: String,
+    },
+    SourceCopied {
+        name: &'a str,
+        version: &'a semver::Version,
+        path: &'a Path,
+    },
+    SourceCloned {
+        url: &'a str,
+        path: &'a Path,
+    },
+    LockUpdated {
+        name: &'a str,
+        #[serde(flatten)]
+        change: LockChange<'a>,
+        offline: bool,
+    },
+    PatchRemoved {
+        name: &'a str,
+        registry: &'a str,
+        manifest_path: &'a Path,
+    },
+    Undone {
+        command: &'a str,
+    },
+    PatchDisabled {
+        name: &'a str,
+        registry: &'a str,
+        manifest_path: &'a Path,
+    },
+    PatchEnabled {
+        name: &'a str,
+        registry: &'a str,
+        manifest_path: &'a Path,
+    },
+    Patch {
+        name: &'a str,
+        registry: &'a str,
+        source: Option<BTreeMap<String, String>>,
+        status: &'a str,
+        manifest_path: &'a Path,
+    },
+    Warning {
+        message: &'a str,
+    },
+}
+
+/// How the entry of a package in `Cargo.lock` changed
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum LockChange<'a> {
+    Updated {
+        from: &'a crate::toml::LockedPackage,
+        to: &'a crate::toml::LockedPackage,
+    },
+    Added {
+        to: &'a crate::toml::LockedPackage,
+    },
+    Removed {
+        from: &'a crate::toml::LockedPackage,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "reason", rename = "error")]
+struct ErrorMessage {
+    code: error::Code,
+    message: String,
+    causes: Vec<String>,
+}
+
+impl fmt::Display for Message<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Message::PatchApplied {
+                krate, registry, ..
+            } => write!(
+                f,
+                "Patched dependency \"{}\" on {} \"{registry}\"",
+                krate.name,
+                registry_kind(registry)
+            ),
+            Message::PatchPreview {
+                krate, registry, ..
+            } => write!(
+                f,
+                "Dry run: patch for dependency \"{}\" on {} \"{registry}\" was not written",
+                krate.name,
+                registry_kind(registry)
+            ),
+            Message::SourceCopied {
+                name,
+                version,
+                path,
+            } => write!(
+                f,
+                "Copied source of \"{name}\" {version} to \"{}\"",
+                path.display()
+            ),
+            Message::SourceCloned { url, path } => {
+                write!(f, "Cloned \"{url}\" to \"{}\"", path.display())
+            }
+            Message::LockUpdated {
+                name,
+                change,
+                offline,
+            } => {
+                match change {
+                    LockChange::Updated { from, to } => {
+                        write!(f, "Updated \"{name}\" in `Cargo.lock` from {from} to {to}")?
+                    }
+                    LockChange::Added { to } => write!(f, "Added \"{name}\" {to} to `Cargo.lock`")?,
+                    LockChange::Removed { from } => {
+                        write!(f, "Removed \"{name}\" {from} from `Cargo.lock`")?
+                    }
+                }
+                if *offline {
+                    write!(f, ", using only crates available offline")?
+                }
+                Ok(())
+            }
+            Message::PatchRemoved { name, registry, .. } => write!(
+                f,
+                "Removed patch for dependency \"{name}\" on {} \"{registry}\"",
+                registry_kind(registry)
+            ),
+            Message::Undone { command } => write!(f, "Undid `{command}`"),
+            Message::PatchDisabled { name, registry, .. } => write!(
+                f,
+                "Disabled patch for dependency \"{name}\" on {} \"{registry}\"",
+                registry_kind(registry)
+            ),
+            Message::PatchEnabled { name, registry, .. } => write!(
+                f,
+                "Enabled patch for dependency \"{name}\" on {} \"{registry}\"",
+                registry_kind(registry)
+            ),
+            Message::Patch {
+                name,
+                registry,
+                status,
+                ..
+            } => write!(f, "{name} {registry} {status}"),
+            Message::Warning { message } => write!(f, "warning: {message}"),
+        }
+    }
+}
+
+/// Patches for git dependencies are keyed on the repository URL, rather than a registry name
+fn registry_kind(registry: &str) -> &'static str {
+    if registry.contains("://") {
+        "source"
+    } else {
+        "registry"
+    }
+}
+
+impl Shell {
+    pub fn new(format: MessageFormat) -> Self {
+        Self { format }
+    }
+
+    pub fn format(&self) -> MessageFormat {
+        self.format
+    }
+
+    /// Prints a message to stderr for humans, or as a line of JSON on stdout
+    pub fn print(&self, message: &Message) {
+        match self.format {
+            MessageFormat::Human => eprintln!("{message}"),
+            MessageFormat::Json => print_json(message),
+        }
+    }
+
+    /// Asks the user to choose one of `options` on the terminal, returning the index of the choice.
+    ///
+    /// Returns `None` without asking when the shell is not interactive,
+    /// as when stdin is not a terminal or messages are printed as JSON,
+    /// and when stdin is closed before a choice is made.
+    pub fn choose(&self, question: &str, options: &[String]) -> anyhow::Result<Option<usize>> {
+        if !self.is_interactive() {
+            return Ok(None);
+        }
+
+        let ask = || -> io::Result<Option<usize>> {
+            let mut stderr = io::stderr().lock();
+
+            writeln!(stderr, "{question}")?;
+            for (number, option) in (1..).zip(options) {
+                writeln!(stderr, "  {number}) {option}")?;
+            }
+
+            let mut answer = String::new();
+
+            loop {
+                write!(stderr, "Enter a number (1-{}): ", options.len())?;
+                stderr.flush()?;
+
+                answer.clear();
+                if io::stdin().lock().read_line(&mut answer)? == 0 {
+                    writeln!(stderr)?;
+                    return Ok(None);
+                }
+
+                match answer.trim().parse::<usize>() {
+                    Ok(number) if (1..=options.len()).contains(&number) => {
+                        return Ok(Some(number - 1))
+                    }
+                    _ => continue,
+                }
+            }
+        };
+
+        ask().context(error::Error::new(
+            error::Code::Io,
+            "failed to ask for a choice on the terminal",
+        ))
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.format == MessageFormat::Human
+            && io::stdin().is_terminal()
+            && io::stderr().is_terminal()
+    }
+
+    pub fn warn(&self, message: impl fmt::Display) {
+        self.print(&Message::Warning {
+            message: &message.to_string(),
+        })
+    }
+
+    pub fn error(&self, error: &anyhow::Error) {
+        match self.format {
+            MessageFormat::Human => match error::snippet(error) {
+                Some(snippet) => {
+                    // Colors are only used when stderr supports them
+                    let _ = writeln!(anstream::stderr(), "{}", render(error, snippet));
+                }
+                None => eprintln!("error: {error:?}"),
+            },
+            MessageFormat::Json => print_json(&ErrorMessage {
+                code: error::code(error),
+                message: error.to_string(),
+                causes: error.chain().skip(1).map(ToString::to_string).collect(),
+            }),
+        }
+    }
+}
+
+fn print_json(message: &impl Serialize) {
+    println!(
+        "{}",
+        serde_json::to_string(message).expect("messages can always be serialized")
+    );
+}
+
+/// Renders an error with an annotated snippet of the file it is about, followed by its causes
+fn render(error: &anyhow::Error, snippet: &error::Snippet) -> String {
+    use annotate_snippets::{Level, Renderer};
+
+    let title = error.to_string();
+    let origin = snippet.path.display().to_string();
+
+    let mut message = Level::Error.title(&title).snippet(
+        annotate_snippets::Snippet::source(&snippet.source)
+            .origin(&origin)
+            .fold(true)
+            .annotation(
+                Level::Error
+                    .span(snippet.span.clone())
+                    .label(&snippet.label),
+            ),
+    );
+
+    if let Some(help) = &snippet.help {
+        message = message.footer(Level::Help.title(help));
+    }
+
+    let mut rendered = Renderer::styled().render(message).to_string();
+
+    let causes = error.chain().skip(1).collect::<Vec<_>>();
+    if !causes.is_empty() {
+        rendered.push_str("\n\nCaused by:");
+        for cause in causes {
+            rendered.push_str(&format!("\n    {cause}"));
+        }
+    }
+
+    rendered
+}
@@ -1,46 +1,691 @@
-use crate::context;
+use crate::{
+    context,
+    error::{Code, Error, Snippet},
+};
 
-use std::{iter::FromIterator, path, path::Path};
+use std::{collections::BTreeMap, fmt, iter::FromIterator, ops::Range, path, path::Path};
 
 use anyhow::{bail, Context as _};
 use cargo_util_schemas::core::GitReference;
 use fs_err as fs;
 use pathdiff::diff_paths;
+use serde::Serialize;
+use url::Url;
 
+/// Adds a patch for `name` to a manifest, or to a cargo config file.
+///
+/// Relative paths are written relative to `manifest_directory`, or made absolute when it is `None`.
+/// An existing, different patch for `name` is only replaced when `conflict` allows it.
+#[allow(clippy::too_many_arguments)]
 pub fn patch_manifest(
     working_dir: &Path,
+    manifest_path: &Path,
     manifest: &str,
-    manifest_directory: &Path,
+    manifest_directory: Option<&Path>,
     name: &str,
     registry: &str,
     mode: &context::Mode,
+    conflict: context::Conflict,
 ) -> anyhow::Result<String> {
-    let mut manifest: toml_edit::DocumentMut = manifest
-        .parse()
-        .context("patch manifest contains invalid toml")?;
+    let original = manifest;
+
+    let mut manifest: toml_edit::DocumentMut = manifest.parse().context(Error::new(
+        Code::InvalidManifest,
+        "patch manifest contains invalid toml",
+    ))?;
+
+    let not_a_table = |keys: &[&str], help: String| {
+        let name = keys.last().expect("at least one key is given");
+
+        Error::new(
+            Code::InvalidManifest,
+            format!("{name} already exists but is not a table"),
+        )
+        .with_snippet(span(original, keys).map(|span| {
+            Snippet::new(
+                manifest_path,
+                working_dir,
+                original,
+                span,
+                "expected a table",
+            )
+            .help(help)
+        }))
+    };
 
     let manifest_table = manifest.as_table_mut();
 
-    let patch_table = create_subtable(manifest_table, "patch", true)?;
+    let Some(patch_table) = create_subtable(manifest_table, "patch", true) else {
+        bail!(not_a_table(
+            &["patch"],
+            "`patch` holds a table for each registry or source that is patched".to_owned()
+        ))
+    };
+
+    let Some(registry_table) = create_subtable(patch_table, registry, false) else {
+        bail!(not_a_table(
+            &["patch", registry],
+            format!(
+                "`patch.{registry}` holds the patched crates, like `{name} = {{ path = \"...\" }}`"
+            )
+        ))
+    };
+
+    let patch = toml_edit::Value::InlineTable(source(working_dir, manifest_directory, mode));
+
+    let mut prefix = None;
+
+    if let Some(existing) = registry_table.get(name) {
+        let existing = existing.clone().into_value().map_or_else(
+            |existing| existing.to_string(),
+            |existing| existing.to_string(),
+        );
+        let existing = existing.trim();
 
-    let registry_table = create_subtable(patch_table, registry, false)?;
+        // Patching with the same source again changes nothing
+        if existing == patch.to_string().trim() {
+            return Ok(manifest.to_string());
+        }
+
+        // Comments above the existing patch are kept along with the new one
+        let decor = registry_table
+            .key(name)
+            .and_then(|key| key.leaf_decor().prefix())
+            .and_then(toml_edit::RawString::as_str)
+            .unwrap_or_default();
+
+        prefix = match conflict {
+            context::Conflict::Refuse => bail!(Error::new(
+                Code::PatchExists,
+                format!("\"{name}\" is already patched with `{existing}`")
+            )
+            .with_snippet(span(original, &["patch", registry, name]).map(|span| {
+                Snippet::new(manifest_path, working_dir, original, span, "existing patch")
+                    .help("use `--replace` to replace it")
+            }))),
+            context::Conflict::Replace => Some(decor.to_owned()),
+            context::Conflict::KeepReplaced => Some(format!("{decor}# {name} = {existing}\n")),
+        };
+    }
+
+    toml_edit::Table::insert(registry_table, name, toml_edit::Item::Value(patch));
 
-    toml_edit::Table::insert(
-        registry_table,
-        name,
-        toml_edit::Item::Value(toml_edit::Value::InlineTable(source(
-            working_dir,
-            manifest_directory,
-            mode,
-        ))),
-    );
+    if let Some(prefix) = prefix {
+        registry_table
+            .key_mut(name)
+            .expect("patch was just inserted")
+            .leaf_decor_mut()
+            .set_prefix(prefix);
+    }
 
     Ok(manifest.to_string())
 }
 
+/// Removes the patch for `name` from the `[patch]` section of a manifest.
+///
+/// Returns the updated manifest, along with the registry the patch was removed from.
+/// Registry tables, and the `[patch]` table itself, are removed once they become empty.
+pub fn remove_patch(
+    manifest: &str,
+    name: &str,
+    registry: Option<&str>,
+) -> anyhow::Result<(String, String)> {
+    let mut manifest: toml_edit::DocumentMut = manifest.parse().context(Error::new(
+        Code::InvalidManifest,
+        "project manifest contains invalid toml",
+    ))?;
+
+    let Some(patch_table) = manifest
+        .get_mut("patch")
+        .and_then(toml_edit::Item::as_table_like_mut)
+    else {
+        bail!(Error::new(
+            Code::PatchNotFound,
+            format!("no patch found for \"{name}\"")
+        ))
+    };
+
+    let registry = find_registry(patch_table, name, registry, "patch", "patched")?;
+
+    let registry_table = patch_table
+        .get_mut(&registry)
+        .and_then(toml_edit::Item::as_table_like_mut)
+        .expect("registry table was found above");
+
+    registry_table.remove(name);
+
+    if registry_table.is_empty() {
+        patch_table.remove(&registry);
+    }
+
+    if patch_table.is_empty() {
+        manifest.remove("patch");
+    }
+
+    Ok((manifest.to_string(), registry))
+}
+
+/// Finds the registry `name` is patched on in the `[patch]` section of a manifest
+pub fn patched_registry(
+    manifest: &str,
+    name: &str,
+    registry: Option<&str>,
+) -> anyhow::Result<String> {
+    let manifest: toml_edit::DocumentMut = manifest.parse().context(Error::new(
+        Code::InvalidManifest,
+        "project manifest contains invalid toml",
+    ))?;
+
+    let Some(patch_table) = manifest
+        .get("patch")
+        .and_then(toml_edit::Item::as_table_like)
+    else {
+        bail!(Error::new(
+            Code::PatchNotFound,
+            format!("no patch found for \"{name}\"")
+        ))
+    };
+
+    find_registry(patch_table, name, registry, "patch", "patched")
+}
+
+/// Finds the registry table in `tables` that holds `name`.
+///
+/// `what` and `state` describe the entries for error messages, like "patch" and "patched".
+fn find_registry(
+    tables: &dyn toml_edit::TableLike,
+    name: &str,
+    registry: Option<&str>,
+    what: &str,
+    state: &str,
+) -> anyhow::Result<String> {
+    let registries = tables
+        .iter()
+        .filter(|(_, registry_table)| {
+            registry_table
+                .as_table_like()
+                .is_some_and(|registry_table| registry_table.contains_key(name))
+        })
+        .map(|(registry, _)| registry.to_owned())
+        .filter(|patched_registry| registry.map_or(true, |registry| registry == patched_registry))
+        .collect::<Vec<_>>();
+
+    Ok(match &registries[..] {
+        [] => match registry {
+            Some(registry) => bail!(Error::new(
+                Code::PatchNotFound,
+                format!("no {what} found for \"{name}\" on registry \"{registry}\"")
+            )),
+            None => bail!(Error::new(
+                Code::PatchNotFound,
+                format!("no {what} found for \"{name}\"")
+            )),
+        },
+        [registry] => registry.clone(),
+        [_, _, ..] => bail!(Error::new(
+            Code::AmbiguousPatch,
+            format!(
+                "\"{name}\" is {state} on multiple registries ({}). \
+                 Choose one with the `--registry` flag",
+                registries.join(", ")
+            )
+        )),
+    })
+}
+
+/// Where disabled patches are kept, under keys that cargo ignores.
+///
+/// Manifests keep them in the workspace's or package's metadata, and cargo config files in a
+/// table of their own. The first `kept` keys are never removed when the stash is emptied.
+fn stash_keys(manifest: &toml_edit::DocumentMut, config: bool) -> (&'static [&'static str], usize) {
+    if config {
+        (&["cargo-override", "disabled"], 0)
+    } else if manifest.contains_key("workspace") {
+        (&["workspace", "metadata", "cargo-override", "disabled"], 1)
+    } else {
+        (&["package", "metadata", "cargo-override", "disabled"], 1)
+    }
+}
+
+/// Moves the patch for `name` out of `[patch]` and into the stash of disabled patches.
+///
+/// `config` is set for cargo config files, which have no metadata tables.
+/// Returns the updated manifest, along with the registry of the patch.
+pub fn disable_patch(
+    manifest: &str,
+    name: &str,
+    registry: Option<&str>,
+    config: bool,
+) -> anyhow::Result<(String, String)> {
+    let mut manifest: toml_edit::DocumentMut = manifest.parse().context(Error::new(
+        Code::InvalidManifest,
+        "project manifest contains invalid toml",
+    ))?;
+
+    let (stash, _) = stash_keys(&manifest, config);
+
+    let mut keys = vec!["patch"];
+
+    let Some(patch_table) = manifest
+        .get_mut("patch")
+        .and_then(toml_edit::Item::as_table_like_mut)
+    else {
+        bail!(Error::new(
+            Code::PatchNotFound,
+            format!("no patch found for \"{name}\"")
+        ))
+    };
+
+    let registry = find_registry(patch_table, name, registry, "patch", "patched")?;
+    keys.push(&registry);
+
+    let (key, patch) = take_entry(manifest.as_table_mut(), &keys, 0, name);
+
+    let end = last_position(manifest.as_table()) + 1;
+
+    let stash_table = stash_table(manifest.as_table_mut(), stash)?;
+
+    let Some(registry_table) = create_subtable(stash_table, &registry, false) else {
+        bail!(Error::new(
+            Code::InvalidManifest,
+            format!("{registry} already exists but is not a table")
+        ))
+    };
+
+    if registry_table.contains_key(name) {
+        bail!(Error::new(
+            Code::PatchExists,
+            format!("a disabled patch for \"{name}\" on registry \"{registry}\" already exists")
+        ))
+    }
+
+    // New tables are placed next to their parent, which would split up the package's keys
+    if registry_table.position().is_none() {
+        registry_table.set_position(end);
+    }
+
+    registry_table.insert_formatted(&key, patch);
+
+    Ok((manifest.to_string(), registry))
+}
+
+/// Moves the patch for `name` from the stash of disabled patches back into `[patch]`.
+///
+/// Returns the updated manifest, along with the registry of the patch.
+pub fn enable_patch(
+    manifest: &str,
+    name: &str,
+    registry: Option<&str>,
+    config: bool,
+) -> anyhow::Result<(String, String)> {
+    let mut manifest: toml_edit::DocumentMut = manifest.parse().context(Error::new(
+        Code::InvalidManifest,
+        "project manifest contains invalid toml",
+    ))?;
+
+    let (stash, kept) = stash_keys(&manifest, config);
+
+    let Some(stash_table) = stash
+        .iter()
+        .try_fold(manifest.as_item(), |item, key| item.get(key))
+        .and_then(toml_edit::Item::as_table_like)
+    else {
+        bail!(Error::new(
+            Code::PatchNotFound,
+            format!("no disabled patch found for \"{name}\"")
+        ))
+    };
+
+    let registry = find_registry(stash_table, name, registry, "disabled patch", "disabled")?;
+
+    let mut keys = stash.to_vec();
+    keys.push(&registry);
+
+    let (key, patch) = take_entry(manifest.as_table_mut(), &keys, kept, name);
+
+    let Some(patch_table) = create_subtable(manifest.as_table_mut(), "patch", true) else {
+        bail!(Error::new(
+            Code::InvalidManifest,
+            "patch already exists but is not a table"
+        ))
+    };
+
+    let Some(registry_table) = create_subtable(patch_table, &registry, false) else {
+        bail!(Error::new(
+            Code::InvalidManifest,
+            format!("{registry} already exists but is not a table")
+        ))
+    };
+
+    if registry_table.contains_key(name) {
+        bail!(Error::new(
+            Code::PatchExists,
+            format!("\"{name}\" is already patched on registry \"{registry}\"")
+        ))
+    }
+
+    registry_table.insert_formatted(&key, patch);
+
+    Ok((manifest.to_string(), registry))
+}
+
+/// Removes the entry `name` from the table at `keys`, keeping its formatting.
+///
+/// Tables left empty are removed too, apart from the first `kept` tables in `keys`.
+fn take_entry(
+    table: &mut toml_edit::Table,
+    keys: &[&str],
+    kept: usize,
+    name: &str,
+) -> (toml_edit::Key, toml_edit::Item) {
+    fn find<'a>(
+        table: &'a mut toml_edit::Table,
+        keys: &[&str],
+    ) -> &'a mut dyn toml_edit::TableLike {
+        keys.iter()
+            .fold(table as &mut dyn toml_edit::TableLike, |table, key| {
+                table
+                    .get_mut(key)
+                    .and_then(toml_edit::Item::as_table_like_mut)
+                    .expect("table was found before")
+            })
+    }
+
+    let entries = find(table, keys);
+    let key = entries.key(name).expect("entry was found before").clone();
+    let item = entries.remove(name).expect("entry was found before");
+
+    for depth in (kept..keys.len()).rev() {
+        let parent = find(table, &keys[..depth]);
+
+        if parent
+            .get(keys[depth])
+            .and_then(toml_edit::Item::as_table_like)
+            .is_some_and(toml_edit::TableLike::is_empty)
+        {
+            parent.remove(keys[depth]);
+        } else {
+            break;
+        }
+    }
+
+    (key, item)
+}
+
+/// The position of the table that is written last in a document
+fn last_position(table: &toml_edit::Table) -> usize {
+    table
+        .iter()
+        .flat_map(|(_, item)| match item {
+            toml_edit::Item::Table(table) => vec![table],
+            toml_edit::Item::ArrayOfTables(tables) => tables.iter().collect(),
+            _ => Vec::new(),
+        })
+        .map(|table| last_position(table).max(table.position().unwrap_or(0)))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Gets the table at `keys`, creating implicit tables for the ones that do not exist
+fn stash_table<'a>(
+    table: &'a mut toml_edit::Table,
+    keys: &[&str],
+) -> anyhow::Result<&'a mut toml_edit::Table> {
+    keys.iter().try_fold(table, |table, key| {
+        let existing = &mut table[key];
+
+        if existing.is_none() {
+            let mut implicit = toml_edit::Table::new();
+            implicit.set_implicit(true);
+            *existing = toml_edit::Item::Table(implicit);
+        }
+
+        existing.as_table_mut().with_context(|| {
+            Error::new(
+                Code::InvalidManifest,
+                format!("{key} already exists but is not a table"),
+            )
+        })
+    })
+}
+
+/// An entry in one of the `[patch]` tables of a manifest
+pub struct Patch {
+    pub registry: String,
+    pub name: String,
+    /// Source the crate is patched with.
+    /// `None` if the entry is not a `path` or `git` source that we understand
+    pub source: Option<context::Mode>,
+}
+
+/// Lists every patch found in the `[patch]` section of a manifest
+pub fn patches(manifest: &str) -> anyhow::Result<Vec<Patch>> {
+    let manifest: toml_edit::DocumentMut = manifest.parse().context(Error::new(
+        Code::InvalidManifest,
+        "project manifest contains invalid toml",
+    ))?;
+
+    let Some(patch_table) = manifest
+        .get("patch")
+        .and_then(toml_edit::Item::as_table_like)
+    else {
+        return Ok(Vec::new());
+    };
+
+    Ok(registry_patches(patch_table))
+}
+
+/// Lists every patch in the stash of disabled patches
+pub fn disabled_patches(manifest: &str, config: bool) -> anyhow::Result<Vec<Patch>> {
+    let manifest: toml_edit::DocumentMut = manifest.parse().context(Error::new(
+        Code::InvalidManifest,
+        "project manifest contains invalid toml",
+    ))?;
+
+    let (stash, _) = stash_keys(&manifest, config);
+
+    let Some(stash_table) = stash
+        .iter()
+        .try_fold(manifest.as_item(), |item, key| item.get(key))
+        .and_then(toml_edit::Item::as_table_like)
+    else {
+        return Ok(Vec::new());
+    };
+
+    Ok(registry_patches(stash_table))
+}
+
+/// Lists the patches in a table of registry tables
+fn registry_patches(tables: &dyn toml_edit::TableLike) -> Vec<Patch> {
+    tables
+        .iter()
+        .filter_map(|(registry, registry_table)| Some((registry, registry_table.as_table_like()?)))
+        .flat_map(|(registry, registry_table)| {
+            registry_table.iter().map(move |(key, entry)| {
+                let entry = entry.as_table_like();
+
+                let name = entry
+                    .and_then(|entry| entry.get("package")?.as_str())
+                    .unwrap_or(key);
+
+                Patch {
+                    registry: registry.to_owned(),
+                    name: name.to_owned(),
+                    source: entry.and_then(patch_source),
+                }
+            })
+        })
+        .collect()
+}
+
+fn patch_source(entry: &dyn toml_edit::TableLike) -> Option<context::Mode> {
+    let get = |key| entry.get(key).and_then(toml_edit::Item::as_str);
+
+    if let Some(path) = get("path") {
+        return Some(context::Mode::Path(path.into()));
+    }
+
+    if let (Some(requirement), Some(_)) = (get("version"), get("registry-index")) {
+        let requirement = requirement.parse().ok()?;
+
+        return Some(context::Mode::Registry { requirement });
+    }
+
+    let url = Url::parse(get("git")?).ok()?;
+
+    let reference = match (get("branch"), get("tag"), get("rev")) {
+        (None, None, None) => GitReference::DefaultBranch,
+        (Some(branch), None, None) => GitReference::Branch(branch.to_owned()),
+        (None, Some(tag), None) => GitReference::Tag(tag.to_owned()),
+        (None, None, Some(rev)) => GitReference::Rev(rev.to_owned()),
+        _ => return None,
+    };
+
+    Some(context::Mode::Git { url, reference })
+}
+
+/// A named group of crates, patched from the same source
+pub struct Profile {
+    /// Paths are relative to the directory of the manifest defining the profile
+    pub source: context::Mode,
+    pub crates: Vec<String>,
+}
+
+/// Reads the profile `name` from `[workspace.metadata.cargo-override.profiles]`,
+/// or from the package's metadata when the manifest has no workspace.
+///
+/// Profiles use the same fields as patches, along with the list of `crates` to patch.
+pub fn profile(manifest: &str, name: &str) -> anyhow::Result<Profile> {
+    let manifest: toml_edit::DocumentMut = manifest.parse().context(Error::new(
+        Code::InvalidManifest,
+        "project manifest contains invalid toml",
+    ))?;
+
+    let table = if manifest.contains_key("workspace") {
+        "workspace"
+    } else {
+        "package"
+    };
+
+    let Some(profile) = [table, "metadata", "cargo-override", "profiles", name]
+        .iter()
+        .try_fold(manifest.as_item(), |item, key| item.get(key))
+    else {
+        bail!(Error::new(
+            Code::ProfileNotFound,
+            format!(
+                "no profile named \"{name}\" found in `[{table}.metadata.cargo-override.profiles]`"
+            )
+        ))
+    };
+
+    let invalid = |message: &str| {
+        Error::new(
+            Code::InvalidManifest,
+            format!("profile \"{name}\" is invalid: {message}"),
+        )
+    };
+
+    let profile = profile
+        .as_table_like()
+        .with_context(|| invalid("expected a table"))?;
+
+    let source = patch_source(profile)
+        .with_context(|| invalid("expected a `path` or `git` source to patch with"))?;
+
+    let crates = profile
+        .get("crates")
+        .and_then(toml_edit::Item::as_array)
+        .and_then(|crates| {
+            crates
+                .iter()
+                .map(|krate| krate.as_str().map(str::to_owned))
+                .collect::<Option<Vec<_>>>()
+        })
+        .filter(|crates| !crates.is_empty())
+        .with_context(|| invalid("expected a list of `crates` to patch"))?;
+
+    Ok(Profile { source, crates })
+}
+
+/// Lists the versions of `name` that are recorded in a `Cargo.lock` file
+pub fn locked_versions(lock: &str, name: &str) -> anyhow::Result<Vec<String>> {
+    let mut versions = locked_packages(lock, name)?
+        .into_iter()
+        .map(|package| package.version)
+        .collect::<Vec<_>>();
+
+    versions.sort();
+    versions.dedup();
+
+    Ok(versions)
+}
+
+/// A package pinned by `Cargo.lock`
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct LockedPackage {
+    pub version: String,
+    /// Packages from a local path have no source
+    pub source: Option<String>,
+}
+
+impl fmt::Display for LockedPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{} ({source})", self.version),
+            None => write!(f, "{} (local path)", self.version),
+        }
+    }
+}
+
+/// Lists every entry for the crate `name` in `Cargo.lock`, sorted by version and source
+pub fn locked_packages(lock: &str, name: &str) -> anyhow::Result<Vec<LockedPackage>> {
+    let lock: toml_edit::DocumentMut = lock.parse().context(Error::new(
+        Code::InvalidManifest,
+        "`Cargo.lock` contains invalid toml",
+    ))?;
+
+    let Some(packages) = lock
+        .get("package")
+        .and_then(toml_edit::Item::as_array_of_tables)
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut locked = packages
+        .iter()
+        .filter(|package| package.get("name").and_then(toml_edit::Item::as_str) == Some(name))
+        .filter_map(|package| {
+            Some(LockedPackage {
+                version: package.get("version")?.as_str()?.to_owned(),
+                source: package
+                    .get("source")
+                    .and_then(toml_edit::Item::as_str)
+                    .map(str::to_owned),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    locked.sort();
+
+    Ok(locked)
+}
+
+/// The fields of the source table written for a patch, as plain strings
+pub fn source_fields(
+    working_dir: &Path,
+    manifest_directory: Option<&Path>,
+    mode: &context::Mode,
+) -> BTreeMap<String, String> {
+    source(working_dir, manifest_directory, mode)
+        .iter()
+        .filter_map(|(key, value)| Some((key.to_owned(), value.as_str()?.to_owned())))
+        .collect()
+}
+
 fn source(
     working_dir: &Path,
-    manifest_directory: &Path,
+    manifest_directory: Option<&Path>,
     mode: &context::Mode,
 ) -> toml_edit::InlineTable {
     match mode {
@@ -48,17 +693,21 @@ fn source(
             let attempt_to_canonicalize =
                 |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
 
-            let [manifest_directory, working_dir] =
-                [manifest_directory, working_dir].map(attempt_to_canonicalize);
+            let path = if let Some(manifest_directory) = manifest_directory {
+                let [manifest_directory, working_dir] =
+                    [manifest_directory, working_dir].map(attempt_to_canonicalize);
 
-            let path = if manifest_directory != working_dir {
-                diff_paths(
-                    path::absolute(working_dir.join(relative_path)).unwrap(),
-                    path::absolute(manifest_directory).unwrap(),
-                )
-                .expect("both paths are absolute")
+                if manifest_directory != working_dir {
+                    diff_paths(
+                        path::absolute(working_dir.join(relative_path)).unwrap(),
+                        path::absolute(manifest_directory).unwrap(),
+                    )
+                    .expect("both paths are absolute")
+                } else {
+                    relative_path.into()
+                }
             } else {
-                relative_path.into()
+                path::absolute(working_dir.join(relative_path)).unwrap()
             };
 
             let path = path
@@ -82,14 +731,21 @@ fn source(
                     .flatten(),
             )
         }
+        context::Mode::Registry { requirement } => toml_edit::InlineTable::from_iter([
+            ("version", requirement.to_string()),
+            ("registry-index", crate::CRATES_IO_SPARSE_INDEX.to_owned()),
+        ]),
     }
 }
 
+/// Gets the table `name`, creating it if it does not exist.
+///
+/// Returns `None` if something other than a table already uses the name.
 fn create_subtable<'a>(
     table: &'a mut toml_edit::Table,
     name: &str,
     dotted: bool,
-) -> anyhow::Result<&'a mut toml_edit::Table> {
+) -> Option<&'a mut toml_edit::Table> {
     let existing = &mut table[name];
 
     if existing.is_none() {
@@ -97,14 +753,61 @@ fn create_subtable<'a>(
         *existing = toml_edit::Item::Table(toml_edit::Table::new());
     }
 
-    // TODO: in the future we may be able to do cool things with miette
-    let _span = existing.span();
-
-    let Some(subtable) = existing.as_table_mut() else {
-        bail!("{name} already exists but is not a table")
-    };
+    let subtable = existing.as_table_mut()?;
 
     subtable.set_dotted(dotted);
 
-    Ok(subtable)
+    Some(subtable)
+}
+
+/// Finds where the item at `keys` is written in a manifest.
+///
+/// Spans are lost once a document is made editable, so the manifest is parsed again here.
+fn span(manifest: &str, keys: &[&str]) -> Option<Range<usize>> {
+    let manifest = toml_edit::ImDocument::parse(manifest).ok()?;
+
+    keys.iter()
+        .try_fold(manifest.as_item(), |item, key| item.get(key))?
+        .span()
+}
+
+/// Finds where the dependency on the crate `name` is declared in a manifest,
+/// from the start of its key to the end of its value.
+pub fn dependency_span(manifest: &str, name: &str) -> Option<Range<usize>> {
+    const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+    let manifest = toml_edit::ImDocument::parse(manifest).ok()?;
+
+    let targets = manifest
+        .get("target")
+        .and_then(toml_edit::Item::as_table_like)
+        .into_iter()
+        .flat_map(|targets| targets.iter().map(|(_, target)| target));
+
+    let span = std::iter::once(manifest.as_item())
+        .chain(targets)
+        .flat_map(|table| DEPENDENCY_TABLES.map(|dependencies| table.get(dependencies)))
+        .flatten()
+        .filter_map(toml_edit::Item::as_table_like)
+        .flat_map(|dependencies| {
+            dependencies
+                .iter()
+                .filter_map(|(key, _)| dependencies.get_key_value(key))
+        })
+        .find(|(key, dependency)| {
+            dependency
+                .get("package")
+                .and_then(toml_edit::Item::as_str)
+                .map_or(key.get() == name, |package| package == name)
+        })
+        .and_then(|(key, dependency)| {
+            let key = key.span()?;
+            let end = dependency
+                .span()
+                .map_or(key.end, |value| value.end.max(key.end));
+
+            Some(key.start..end)
+        });
+
+    span
 }
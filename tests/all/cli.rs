@@ -4,7 +4,7 @@ use clap::{CommandFactory, Parser};
 use fake::{Fake, Faker};
 use googletest::{
     expect_that,
-    matchers::{eq, matches_pattern, ok, some},
+    matchers::{displays_as, eq, matches_pattern, ok, some},
 };
 
 use cargo_override::{cli, CargoInvocation, Cli};
@@ -33,6 +33,49 @@ fn path_parse_from_args() {
     }
 }
 
+#[googletest::test]
+fn registry_version_parse_from_args() {
+    for (requirement, parsed) in [
+        ("1.0.82", "=1.0.82"),
+        ("1.0.0-rc.1", "=1.0.0-rc.1"),
+        (">=1.0.81, <1.0.83", ">=1.0.81, <1.0.83"),
+        ("^1.0", "^1.0"),
+    ] {
+        let output = Cli::try_parse_from([
+            "cargo",
+            "override",
+            "--package",
+            "anyhow",
+            "--registry-version",
+            requirement,
+        ]);
+
+        expect_that!(
+            output,
+            ok(matches_pattern!(Cli {
+                command: matches_pattern!(CargoInvocation::Override(matches_pattern!(
+                    cli::Override {
+                        source: matches_pattern!(cli::Source {
+                            registry_version: some(displays_as(eq(parsed)))
+                        })
+                    }
+                )))
+            }))
+        )
+    }
+}
+
+#[googletest::test]
+fn version_flag_prints_version() {
+    for base_command in ["cargo override", "cargo-override"] {
+        let output = Cli::try_parse_from([base_command, "override", "--version"]);
+
+        let error = output.expect_err("`--version` comes up as an `Result::Err`");
+
+        expect_that!(error.kind(), eq(clap::error::ErrorKind::DisplayVersion));
+    }
+}
+
 #[googletest::test]
 fn override_subcommand_help_message() {
     insta::allow_duplicates! {
@@ -43,24 +86,55 @@ fn override_subcommand_help_message() {
 
             let output = strip_ansi_escapes::strip_str(format!("{}", output.render().ansi()));
 
-            insta::assert_snapshot!(output, @r###"
+            insta::assert_snapshot!(output, @r#"
             Quickly override dependencies using the `[patch]` section of `Cargo.toml`s.
 
-            Usage: cargo override [OPTIONS] <--path <PATH>|--git <URI>>
+            Usage: cargo override [OPTIONS] <--path <PATH>|--git <URI>|--registry-version <REQ>>
+                   cargo override [OPTIONS] <COMMAND>
+
+            Commands:
+              remove
+                      Remove an existing patch from `Cargo.toml`
+              list
+                      List the patches in `Cargo.toml`, and whether cargo uses them
+              edit
+                      Copy the source of a dependency into a local directory, and patch it with that directory
+              disable
+                      Disable a patch, keeping it in `Cargo.toml` so that it can be enabled again
+              enable
+                      Enable a patch that was disabled
+              undo
+                      Undo the changes made by the last commands, restoring the files they changed
+              profile
+                      Apply or remove a group of patches defined in `[workspace.metadata.cargo-override.profiles]`
+              help
+                      Print this message or the help of the given subcommand(s)
 
             Options:
                   --path <PATH>
                       Path to patched dependency, to use in override
                   --git <URI>
                       Git URL to source override from
+                  --registry-version <REQ>
+                      Version requirement of the registry version to override with, for the crate chosen with `--package`. A bare version, such as `1.2.3`, means exactly that version
                   --branch <BRANCH>
                       Branch to use when overriding from git
                   --tag <TAG>
                       Tag to use when overriding from git
                   --rev <REV>
                       Specific commit to use when overriding from git
+                  --clone-to <DIR>
+                      Clone the git source into this directory, and patch with the clone, so that it can be edited
+                  --new-branch <BRANCH>
+                      Create a branch with this name in the clone made with `--clone-to`
+                  --package <PACKAGE>
+                      Name of the package to patch with, when the source contains more than one. By default, the package the project depends on is used
+                  --all
+                      Patch every crate in the source's workspace that the project depends on
                   --registry <REGISTRY>
                       Name of the registry to use. Usually `cargo-override` can correctly determine which registry to use without needing this flag
+                  --index <URL>
+                      Index URL of the registry to use, for registries without a name in any cargo config. The patch is written under the URL
                   --manifest-path <MANIFEST_PATH>
                       Path to the `Cargo.toml` file that needs patching. By default, `cargo-override` searches for the `Cargo.toml` file in the current directory or any parent directory
                   --locked
@@ -69,13 +143,25 @@ fn override_subcommand_help_message() {
                       Prevents cargo from accessing the network
                   --frozen
                       Equivalent to specifying both --locked and --offline
+                  --config <KEY=VALUE|PATH>
+                      Override a cargo configuration value, like `cargo --config`
+                  --location <LOCATION>
+                      Where patches are written to, and read from. `manifest` is the workspace `Cargo.toml`, `config` is `.cargo/config.toml` in the workspace, and `user` is `config.toml` in the cargo home directory [default: manifest] [possible values: manifest, config, user]
+                  --message-format <MESSAGE_FORMAT>
+                      Format of the messages printed by `cargo-override` [default: human] [possible values: human, json]
+                  --replace
+                      Replace an existing patch for the crate
+                  --keep-replaced
+                      Keep the replaced patch in the file, commented out, so that it can be restored
                   --force
                       Force the override, ignoring compatibility checks
+                  --dry-run
+                      Print the changes that would be made to `Cargo.toml`, without writing them
               -h, --help
                       Print help
               -V, --version
                       Print version
-            "###);
+            "#);
         }
     }
 }
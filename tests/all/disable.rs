@@ -0,0 +1,198 @@
+//! Tests involving `cargo override disable` and `cargo override enable`
+
+use super::create_cargo_manifest;
+use super::manifest::{Dependency, Header, Manifest, Target};
+
+use std::path::Path;
+
+use assert_cmd::Command;
+use fs_err as fs;
+use googletest::{expect_eq, verify_eq, verify_that};
+use tempfile::TempDir;
+
+#[googletest::test]
+fn disable_and_enable_patch() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let mut manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    manifest.push_str(
+        r#"
+[patch.crates-io]
+# Fixes the backtrace bug
+anyhow = { git = "https://github.com/dtolnay/anyhow", rev = "1234567" }
+"#,
+    );
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = toggle_override("disable", "anyhow", working_dir);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    Disabled patch for dependency "anyhow" on registry "crates-io"
+    "#);
+
+    let disabled_manifest = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    insta::assert_toml_snapshot!(disabled_manifest, @r##"
+    '''
+    [package]
+    name = "package-name"
+    version = "0.1.0"
+    edition = "2021"
+
+    # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+    [dependencies]
+    anyhow = "1.0.86"
+
+    [[bin]]
+    name = "package-name"
+    path = "src/main.rs"
+
+    [package.metadata.cargo-override.disabled.crates-io]
+    # Fixes the backtrace bug
+    anyhow = { git = "https://github.com/dtolnay/anyhow", rev = "1234567" }
+    '''
+    "##);
+
+    let mut command = toggle_override("enable", "anyhow", working_dir);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stderr, @r#"
+    Enabled patch for dependency "anyhow" on registry "crates-io"
+    "#);
+
+    let enabled_manifest = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    expect_eq!(enabled_manifest, manifest);
+}
+
+#[googletest::test]
+fn disable_patch_in_workspace() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let member_path = working_dir.join("member");
+    fs::create_dir(&member_path).expect("failed to create member folder");
+
+    let _ = create_cargo_manifest(
+        &member_path,
+        &Manifest::new(Header::basic("member"))
+            .add_target(Target::lib("member", "src/lib.rs"))
+            .add_dependency(Dependency::new("anyhow", "1.0.86"))
+            .render(),
+    );
+
+    let working_dir_manifest_path = create_cargo_manifest(
+        working_dir,
+        r#"[workspace]
+members = ["member"]
+resolver = "2"
+
+[patch.crates-io]
+anyhow = { path = "anyhow" }
+"#,
+    );
+
+    let mut command = toggle_override("disable", "anyhow", working_dir);
+
+    let assert = command.assert();
+
+    assert.success();
+
+    let manifest = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    insta::assert_toml_snapshot!(manifest, @r#"
+    '''
+    [workspace]
+    members = ["member"]
+    resolver = "2"
+
+    [workspace.metadata.cargo-override.disabled.crates-io]
+    anyhow = { path = "anyhow" }
+    '''
+    "#);
+
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args(["override", "list"])
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @r#"
+    anyhow  crates-io  path: anyhow  disabled
+    "#);
+}
+
+#[googletest::test]
+fn enable_missing_patch_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    let _ = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = toggle_override("enable", "anyhow", working_dir);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: no disabled patch found for "anyhow"
+    "#);
+}
+
+fn toggle_override(subcommand: &str, name: &str, working_dir: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("cargo-override").unwrap();
+    cmd.current_dir(working_dir)
+        .args(["override", subcommand, name])
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
+
+    cmd
+}
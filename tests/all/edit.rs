@@ -0,0 +1,257 @@
+//! Tests involving `cargo override edit`
+
+use super::manifest::{Dependency, Header, Manifest, Target};
+use super::{create_cargo_manifest, vendor_crates};
+
+use std::path::Path;
+
+use assert_cmd::Command;
+use fs_err as fs;
+use googletest::{
+    expect_that,
+    matchers::{contains_substring, eq, not},
+};
+use tempfile::TempDir;
+
+#[googletest::test]
+fn edit_dependency() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = edit_crate("anyhow", working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::with_settings!({filters => vec![
+        (working_dir.to_str().unwrap(), "[TEMPDIR]"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Copied source of "anyhow" 1.0.86 to "[TEMPDIR]/patches/anyhow"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.0.86 (local path)
+        "#);
+    });
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    insta::with_settings!({filters => vec![
+        (r#"[\"\']patches[\/\\]anyhow[\"\']"#, "[PATH]"),
+    ]}, {
+        insta::assert_toml_snapshot!(manifest, @r##"
+        '''
+        [package]
+        name = "package-name"
+        version = "0.1.0"
+        edition = "2021"
+
+        # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+        [dependencies]
+        anyhow = "1.0.86"
+
+        [[bin]]
+        name = "package-name"
+        path = "src/main.rs"
+
+        [patch.crates-io]
+        anyhow = { path = [PATH] }
+        '''
+        "##);
+    });
+
+    let copied = working_dir.join("patches").join("anyhow");
+
+    expect_that!(copied.join("Cargo.toml").is_file(), eq(true));
+    expect_that!(copied.join(".cargo-checksum.json").exists(), eq(false));
+}
+
+#[googletest::test]
+fn edit_dependency_into_dir() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = edit_crate("anyhow", working_dir, |command| {
+        command.args(["--dir", "local-anyhow"])
+    });
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_that!(
+        manifest.ends_with("[patch.crates-io]\nanyhow = { path = \"local-anyhow\" }\n"),
+        eq(true)
+    );
+}
+
+#[googletest::test]
+fn edit_missing_dependency_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .render();
+
+    let _ = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = edit_crate("anyhow", working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: Unable to find dependency on crate "anyhow"
+    "#);
+
+    expect_that!(working_dir.join("patches").exists(), eq(false));
+}
+
+#[googletest::test]
+fn edit_dependency_that_can_not_be_patched_removes_copy() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    // A patch cargo does not use, because its version does not fit
+    let other_anyhow = working_dir.join("other-anyhow");
+    fs::create_dir(&other_anyhow).unwrap();
+    create_cargo_manifest(
+        &other_anyhow,
+        &Manifest::new(Header::basic("anyhow").version("2.0.0".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render()
+        + "\n[patch.crates-io]\nanyhow = { path = \"other-anyhow\" }\n";
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = edit_crate("anyhow", working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::with_settings!({filters => vec![
+        (working_dir.to_str().unwrap(), "[TEMPDIR]"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Copied source of "anyhow" 1.0.86 to "[TEMPDIR]/patches/anyhow"
+        error: "anyhow" is already patched with `{ path = "other-anyhow" }`
+          --> Cargo.toml:16:10
+           |
+        16 | anyhow = { path = "other-anyhow" }
+           |          ^^^^^^^^^^^^^^^^^^^^^^^^^ existing patch
+           |
+           = help: use `--replace` to replace it
+        "#);
+    });
+
+    expect_that!(
+        working_dir.join("patches").join("anyhow").exists(),
+        eq(false)
+    );
+    expect_that!(
+        fs::read_to_string(working_dir_manifest_path).unwrap(),
+        eq(&manifest)
+    );
+
+    // Nothing is left in the way of trying again
+    let assert = edit_crate("anyhow", working_dir, |command| command).assert();
+
+    expect_that!(
+        String::from_utf8(assert.get_output().stderr.clone()).unwrap(),
+        not(contains_substring("already exists"))
+    );
+}
+
+fn edit_crate(
+    name: &str,
+    working_dir: &Path,
+    args: impl Fn(&mut Command) -> &mut Command,
+) -> Command {
+    let mut cmd = Command::cargo_bin("cargo-override").unwrap();
+    args(
+        cmd.current_dir(working_dir)
+            .arg("override")
+            .arg("edit")
+            .arg(name),
+    )
+    .env("CARGO_HOME", working_dir)
+    .env_remove("RUST_BACKTRACE");
+
+    cmd
+}
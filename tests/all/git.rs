@@ -1,14 +1,15 @@
 //! Tests involving `--git` overrides
 
-use super::create_cargo_manifest;
 use super::manifest::{Dependency, Header, Manifest, Target};
+use super::{create_cargo_manifest, create_workspace, vendor_crates};
 
 use std::{env, path::Path};
 
 use assert_cmd::Command;
 use fs_err as fs;
-use googletest::{expect_eq, verify_eq, verify_that};
+use googletest::{expect_eq, expect_that, matchers::eq, verify_eq, verify_that};
 use tempfile::TempDir;
+use url::Url;
 
 #[googletest::test]
 fn git_patch() {
@@ -264,6 +265,12 @@ fn git_patch_version_missmatch() {
     insta::assert_snapshot!(stdout, @"");
     insta::assert_snapshot!(stderr, @r###"
     error: patch could not be applied because version is incompatible
+     --> Cargo.toml:9:1
+      |
+    9 | redact = "0.2.0"
+      | ^^^^^^^^^^^^^^^^ requires `^0.2.0`
+      |
+      = help: the patch has version 0.1.0-pre0. Use `--force` to apply it anyway
     "###);
 
     let manifest_after = fs::read_to_string(&manifest_path).unwrap();
@@ -288,3 +295,346 @@ fn override_redact_crate(
 
     cmd
 }
+
+#[googletest::test]
+fn git_patch_workspace_picks_dependency() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    let repo = working_dir.join("repo");
+    create_workspace(&repo, &[("anyhow", "1.1.5"), ("redact", "0.1.11")]);
+    commit_all(&repo);
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let repo_url = Url::from_directory_path(&repo).unwrap();
+
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args(["override", "--git", repo_url.as_str()])
+        .env_remove("RUST_BACKTRACE")
+        .env("CARGO_HOME", working_dir);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::with_settings!({filters => vec![
+        (repo_url.as_str(), "[REPO_URL]"),
+        (r"#[0-9a-f]{40}", "#[COMMIT]"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (git+[REPO_URL]#[COMMIT])
+        "#);
+    });
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    insta::with_settings!({filters => vec![
+        (repo_url.as_str(), "[REPO_URL]"),
+    ]}, {
+        insta::assert_toml_snapshot!(manifest, @r##"
+        '''
+        [package]
+        name = "package-name"
+        version = "0.1.0"
+        edition = "2021"
+
+        # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+        [dependencies]
+        anyhow = "1.0.86"
+
+        [[bin]]
+        name = "package-name"
+        path = "src/main.rs"
+
+        [patch.crates-io]
+        anyhow = { git = "[REPO_URL]" }
+        '''
+        "##);
+    });
+}
+
+#[googletest::test]
+fn patch_git_dependency() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let upstream = working_dir.join("upstream");
+    create_workspace(&upstream, &[("anyhow", "1.0.86")]);
+    commit_all(&upstream);
+
+    let upstream_url = Url::from_directory_path(&upstream).unwrap();
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    let _ = create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86").git(&upstream_url))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args(["override", "--path", "anyhow"])
+        .env_remove("RUST_BACKTRACE")
+        .env("CARGO_HOME", working_dir);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    insta::assert_snapshot!(stdout, @"");
+
+    insta::with_settings!({filters => vec![
+        (upstream_url.as_str(), "[UPSTREAM_URL]"),
+        (r"#[0-9a-f]{40}", "#[COMMIT]"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on source "[UPSTREAM_URL]"
+        Updated "anyhow" in `Cargo.lock` from 1.0.86 (git+[UPSTREAM_URL]#[COMMIT]) to 1.1.5 (local path)
+        "#);
+    });
+
+    insta::with_settings!({filters => vec![
+        (upstream_url.as_str(), "[UPSTREAM_URL]"),
+    ]}, {
+        insta::assert_toml_snapshot!(manifest, @r##"
+        '''
+        [package]
+        name = "package-name"
+        version = "0.1.0"
+        edition = "2021"
+
+        # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+        [dependencies]
+        anyhow = { version = "1.0.86", git = "[UPSTREAM_URL]" }
+
+        [[bin]]
+        name = "package-name"
+        path = "src/main.rs"
+
+        [patch."[UPSTREAM_URL]"]
+        anyhow = { path = "anyhow" }
+        '''
+        "##);
+    });
+}
+
+#[googletest::test]
+fn git_patch_clone_to() -> googletest::Result<()> {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    let repo = working_dir.join("repo");
+    create_workspace(&repo, &[("anyhow", "1.1.5"), ("redact", "0.1.11")]);
+    commit_all(&repo);
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let repo_url = Url::from_directory_path(&repo).unwrap();
+
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args(["override", "--git", repo_url.as_str()])
+        .args(["--clone-to", "anyhow-clone", "--new-branch", "fix"])
+        .env_remove("RUST_BACKTRACE")
+        .env("CARGO_HOME", working_dir);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::with_settings!({filters => vec![
+        (repo_url.as_str(), "[REPO_URL]"),
+        (working_dir.to_str().unwrap(), "[TEMPDIR]"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Cloned "[REPO_URL]" to "[TEMPDIR]/anyhow-clone"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+        "#);
+    });
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    verify_that!(
+        manifest.ends_with("[patch.crates-io]\nanyhow = { path = \"anyhow-clone/anyhow\" }\n"),
+        eq(true)
+    )?;
+
+    let clone = working_dir.join("anyhow-clone");
+
+    let branch = std::process::Command::new("git")
+        .args(["branch", "--show-current"])
+        .current_dir(&clone)
+        .output()
+        .expect("failed to run git");
+
+    verify_eq!(String::from_utf8(branch.stdout).unwrap(), "fix\n")?;
+
+    let remote = std::process::Command::new("git")
+        .args(["remote", "get-url", "origin"])
+        .current_dir(&clone)
+        .output()
+        .expect("failed to run git");
+
+    verify_eq!(
+        String::from_utf8(remote.stdout).unwrap(),
+        format!("{repo_url}\n")
+    )
+}
+
+#[googletest::test]
+fn git_patch_clone_to_incompatible_removes_clone() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    let repo = working_dir.join("repo");
+    create_workspace(&repo, &[("anyhow", "2.0.0")]);
+    commit_all(&repo);
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let repo_url = Url::from_directory_path(&repo).unwrap();
+
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args(["override", "--git", repo_url.as_str()])
+        .args(["--clone-to", "anyhow-clone"])
+        .env_remove("RUST_BACKTRACE")
+        .env("CARGO_HOME", working_dir);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::with_settings!({filters => vec![
+        (repo_url.as_str(), "[REPO_URL]"),
+        (working_dir.to_str().unwrap(), "[TEMPDIR]"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Cloned "[REPO_URL]" to "[TEMPDIR]/anyhow-clone"
+        error: patch could not be applied because version is incompatible
+         --> Cargo.toml:9:1
+          |
+        9 | anyhow = "1.0.86"
+          | ^^^^^^^^^^^^^^^^^ requires `^1.0.86`
+          |
+          = help: the patch has version 2.0.0. Use `--force` to apply it anyway
+        "#);
+    });
+
+    expect_that!(working_dir.join("anyhow-clone").exists(), eq(false));
+    expect_that!(
+        fs::read_to_string(working_dir_manifest_path).unwrap(),
+        eq(&manifest)
+    );
+}
+
+/// Turns `dir` into a git repository, with everything in it committed
+fn commit_all(dir: &Path) {
+    for args in [
+        &["init", "--quiet"][..],
+        &["add", "."],
+        &[
+            "-c",
+            "user.name=cargo-override",
+            "-c",
+            "user.email=cargo-override@example.com",
+            "commit",
+            "--quiet",
+            "--message",
+            "initial commit",
+        ],
+    ] {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .expect("failed to run git");
+
+        assert!(status.success(), "git {args:?} failed");
+    }
+}
@@ -0,0 +1,102 @@
+//! Tests involving `--message-format json`
+
+use super::manifest::{Dependency, Header, Manifest, Target};
+use super::{create_cargo_manifest, override_path, vendor_crates};
+
+use assert_cmd::Command;
+use fs_err as fs;
+use tempfile::TempDir;
+
+#[googletest::test]
+fn patch_json() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    let patch_crate_name = "anyhow";
+    let patch_folder = patch_crate_name.to_string();
+    let patch_folder_path = working_dir.join(patch_folder.clone());
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new(patch_crate_name, "1.0.86"))
+        .render();
+
+    let _ = create_cargo_manifest(working_dir, &manifest);
+    let _patch_manifest_path = create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic(patch_crate_name).version("1.1.5".to_owned()))
+            .add_target(Target::lib(patch_crate_name, "src/lib.rs"))
+            .render(),
+    );
+
+    let mut command = override_path(&patch_folder, working_dir, |command| {
+        command.args(["--message-format", "json"])
+    });
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::with_settings!({filters => vec![
+        (working_dir.to_str().unwrap(), "[TEMPDIR]")
+    ]}, {
+        insta::assert_snapshot!(stdout, @r#"
+        {"reason":"patch-applied","crate":{"name":"anyhow","version":"1.1.5"},"registry":"crates-io","source":{"path":"anyhow"},"manifest_path":"[TEMPDIR]/Cargo.toml"}
+        {"reason":"lock-updated","name":"anyhow","from":{"version":"1.0.86","source":"registry+https://github.com/rust-lang/crates.io-index"},"to":{"version":"1.1.5","source":null},"offline":false}
+        "#);
+    });
+    insta::assert_snapshot!(stderr, @"");
+}
+
+#[googletest::test]
+fn remove_missing_patch_json() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    let _ = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args(["override", "--message-format", "json", "remove", "anyhow"])
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @r#"
+    {"reason":"error","code":"patch-not-found","message":"no patch found for \"anyhow\"","causes":[]}
+    "#);
+    insta::assert_snapshot!(stderr, @"");
+}
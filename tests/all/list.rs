@@ -0,0 +1,117 @@
+//! Tests involving `cargo override list`
+
+use super::manifest::{Dependency, Header, Manifest, Target};
+use super::{create_cargo_manifest, vendor_crates};
+
+use std::path::Path;
+
+use assert_cmd::Command;
+use fs_err as fs;
+use tempfile::TempDir;
+
+#[googletest::test]
+fn list_patches() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    for (name, version) in [("anyhow", "1.1.5"), ("redact", "0.1.10")] {
+        let patch_folder_path = working_dir.join(name);
+
+        fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+        let _patch_manifest_path = create_cargo_manifest(
+            &patch_folder_path,
+            &Manifest::new(Header::basic(name).version(version.to_owned()))
+                .add_target(Target::lib(name, "src/lib.rs"))
+                .render(),
+        );
+    }
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let mut manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    manifest.push_str(
+        r#"
+[patch.crates-io]
+anyhow = { path = "anyhow" }
+redact = { path = "redact" }
+"#,
+    );
+
+    let _ = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = list_overrides(working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @r#"
+    anyhow  crates-io  path: anyhow  used
+    redact  crates-io  path: redact  unused
+    "#);
+    insta::assert_snapshot!(stderr, @"");
+}
+
+#[googletest::test]
+fn list_without_patches() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .render();
+
+    let _ = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = list_overrides(working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::with_settings!({filters => vec![
+        (r"\/tmp\/\.tmp.*\/", "[TEMPDIR]"),
+        (r"\/private\/var\/.*\/\.tmp.*\/", "[TEMPDIR]"),
+        (r"\/var\/.*\/\.tmp.*\/", "[TEMPDIR]"),
+        (r"C\:\\Users\\.*\\Temp\\\.tmp.*\\", "[TEMPDIR]"),
+    ]}, {
+        insta::assert_snapshot!(stdout, @"");
+        insta::assert_snapshot!(stderr, @r#"
+        No patches found in "[TEMPDIR]Cargo.toml"
+        "#);
+    });
+}
+
+fn list_overrides(working_dir: &Path, args: impl Fn(&mut Command) -> &mut Command) -> Command {
+    let mut cmd = Command::cargo_bin("cargo-override").unwrap();
+    args(cmd.current_dir(working_dir).arg("override").arg("list"))
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
+
+    cmd
+}
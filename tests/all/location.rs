@@ -0,0 +1,259 @@
+//! Tests involving `--location`
+
+use super::manifest::{Dependency, Header, Manifest, Target};
+use super::{create_cargo_manifest, override_path, LOCKED_VERSION};
+
+use std::path::Path;
+
+use assert_cmd::Command;
+use fs_err as fs;
+use googletest::{expect_eq, expect_that, matchers::eq, verify_eq, verify_that};
+use tempfile::TempDir;
+
+#[googletest::test]
+fn patch_to_workspace_config() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let patch_crate_name = "anyhow";
+    let patch_folder = patch_crate_name.to_string();
+    let project_folder = working_dir.join("subdir");
+    let patch_folder_path = working_dir.join(patch_folder.clone());
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    fs::create_dir(&project_folder).expect("failed to create project folder");
+
+    let manifest = project_manifest(patch_crate_name);
+
+    let project_manifest_path = create_cargo_manifest(&project_folder, &manifest);
+    create_patch_crate(&patch_folder_path, patch_crate_name);
+
+    let mut command = override_path(&patch_folder, working_dir, |command| {
+        command
+            .arg("--manifest-path")
+            .arg(project_manifest_path.as_os_str())
+            .args(["--location", "config"])
+    });
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::with_settings!({filters => vec![
+        LOCKED_VERSION,
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from [VERSION] (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+        "#);
+    });
+
+    let manifest_after = fs::read_to_string(&project_manifest_path).unwrap();
+
+    expect_eq!(manifest, manifest_after);
+
+    let config = fs::read_to_string(project_folder.join(".cargo").join("config.toml")).unwrap();
+
+    insta::with_settings!({filters => vec![
+        (r#"[\"\']\.\.[\/\\]anyhow[\"\']"#, "[PATH]"),
+    ]}, {
+        insta::assert_toml_snapshot!(config, @r#"
+        '''
+        [patch.crates-io]
+        anyhow = { path = [PATH] }
+        '''
+        "#);
+    });
+}
+
+#[googletest::test]
+fn patch_to_user_config() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let patch_crate_name = "anyhow";
+    let patch_folder = patch_crate_name.to_string();
+    let patch_folder_path = working_dir.join(patch_folder.clone());
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    let manifest = project_manifest(patch_crate_name);
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+    create_patch_crate(&patch_folder_path, patch_crate_name);
+
+    // `override_path` sets `CARGO_HOME` to the working directory
+    let mut command = override_path(&patch_folder, working_dir, |command| {
+        command.args(["--location", "user"])
+    });
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::with_settings!({filters => vec![
+        LOCKED_VERSION,
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from [VERSION] (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+        "#);
+    });
+
+    let manifest_after = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest, manifest_after);
+
+    let config = fs::read_to_string(working_dir.join("config.toml")).unwrap();
+
+    let patch_path = fs::canonicalize(&patch_folder_path).unwrap();
+
+    expect_that!(
+        config,
+        eq(&format!(
+            "[patch.crates-io]\nanyhow = {{ path = {} }}\n",
+            toml_edit::Value::from(patch_path.to_str().unwrap())
+        ))
+    );
+}
+
+#[googletest::test]
+fn list_and_remove_from_workspace_config() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let manifest = project_manifest("anyhow");
+
+    let _ = create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_patch_crate(&patch_folder_path, "anyhow");
+
+    fs::create_dir(working_dir.join(".cargo")).expect("failed to create config folder");
+    fs::write(
+        working_dir.join(".cargo").join("config.toml"),
+        r#"[patch.crates-io]
+anyhow = { path = "anyhow" }
+"#,
+    )
+    .unwrap();
+
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args(["override", "list", "--location", "config"])
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @r#"
+    anyhow  crates-io  path: anyhow  used
+    "#);
+
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args(["override", "remove", "anyhow", "--location", "config"])
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stderr, @r#"
+    Removed patch for dependency "anyhow" on registry "crates-io"
+    "#);
+
+    let config = fs::read_to_string(working_dir.join(".cargo").join("config.toml")).unwrap();
+
+    expect_eq!(config, "");
+}
+
+#[googletest::test]
+fn patch_to_config_with_invalid_patch_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let manifest = project_manifest("anyhow");
+
+    let _ = create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_patch_crate(&patch_folder_path, "anyhow");
+
+    let config = "[patch]\ncrates-io = \"anyhow\"\n";
+
+    fs::create_dir(working_dir.join(".cargo")).expect("failed to create config folder");
+    fs::write(working_dir.join(".cargo").join("config.toml"), config).unwrap();
+
+    let mut command = override_path("anyhow", working_dir, |command| {
+        command.args(["--location", "config"])
+    });
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: crates-io already exists but is not a table
+     --> .cargo/config.toml:2:13
+      |
+    2 | crates-io = "anyhow"
+      |             ^^^^^^^^ expected a table
+      |
+      = help: `patch.crates-io` holds the patched crates, like `anyhow = { path = "..." }`
+    "#);
+
+    let config_after = fs::read_to_string(working_dir.join(".cargo").join("config.toml")).unwrap();
+
+    expect_eq!(config, config_after);
+}
+
+fn project_manifest(dependency: &str) -> String {
+    let package_name = "package-name";
+    Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new(dependency, "1.0.86"))
+        .render()
+}
+
+fn create_patch_crate(path: &Path, name: &str) {
+    let _patch_manifest_path = create_cargo_manifest(
+        path,
+        &Manifest::new(Header::basic(name).version("1.1.5".to_owned()))
+            .add_target(Target::lib(name, "src/lib.rs"))
+            .render(),
+    );
+}
@@ -1,8 +1,16 @@
 pub mod checksum;
 #[path = "cli.rs"]
 mod cli_tests;
+mod disable;
+mod edit;
 mod git;
+mod json;
+mod list;
+mod location;
 pub mod manifest;
+mod profile;
+mod remove;
+mod undo;
 
 use checksum::Checksum;
 use manifest::{Dependency, Header, Manifest, Target};
@@ -15,15 +23,26 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use cargo_override::CARGO_TOML;
+use cargo_override::{CARGO_LOCK, CARGO_TOML};
 
 use assert_cmd::Command;
 use fake::{Fake, Faker};
 use fs_err as fs;
-use googletest::{expect_eq, verify_eq, verify_that};
+use googletest::{
+    expect_eq, expect_that,
+    matchers::{contains_substring, ends_with, eq, not},
+    verify_eq, verify_that,
+};
 use tempfile::TempDir;
 use test_case::test_case;
 
+/// Filters the version of a crate that cargo locks from crates.io before it is patched,
+/// which is whatever version was published last
+const LOCKED_VERSION: (&str, &str) = (
+    r"from \d+\.\d+\.\d+\S* (\(registry\+https://github\.com/rust-lang/crates\.io-index\))",
+    "from [VERSION] $1",
+);
+
 #[googletest::test]
 fn patch_transative_on_regisrty() {
     let working_dir = TempDir::new().unwrap();
@@ -120,9 +139,10 @@ fn patch_transative_on_regisrty() {
     assert.success();
 
     insta::assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
+    insta::assert_snapshot!(stderr, @r#"
     Patched dependency "anyhow" on registry "private-registry"
-    "###);
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://dl.cloudsmith.io/basic/private/registry/cargo/index.git) to 1.1.5 (local path)
+    "#);
 
     let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
 
@@ -148,6 +168,260 @@ fn patch_transative_on_regisrty() {
     "###);
 }
 
+/// Crates resolved through a vendored copy of crates.io are patched on crates.io
+#[googletest::test]
+fn patch_transitive_through_vendored_sources() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [
+            (
+                "foo",
+                Manifest::new(Header::basic("foo"))
+                    .add_dependency(Dependency::new("anyhow", "1.0.86")),
+            ),
+            (
+                "anyhow",
+                Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+            ),
+        ],
+    );
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("foo", "0.1.0"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let output = override_path("anyhow", working_dir, |command| command)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+    "#);
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_that!(
+        manifest,
+        ends_with("[patch.crates-io]\nanyhow = { path = \"anyhow\" }\n")
+    );
+}
+
+/// A registry that also replaces crates.io is still patched under its own name
+/// when a dependency declares it
+/// A transitive dependency that is already patched resolves to the patch's path,
+/// so replacing the patch has to find its registry from the existing patch
+#[googletest::test]
+fn patch_transitive_replace_existing_patch() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [
+            (
+                "foo",
+                Manifest::new(Header::basic("foo"))
+                    .add_dependency(Dependency::new("anyhow", "1.0.86")),
+            ),
+            (
+                "anyhow",
+                Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+            ),
+        ],
+    );
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("foo", "0.1.0"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    for (folder, version) in [("anyhow", "1.1.5"), ("new-anyhow", "1.1.6")] {
+        let patch_folder_path = working_dir.join(folder);
+        fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+        create_cargo_manifest(&patch_folder_path, &anyhow_manifest(version));
+    }
+
+    override_path("anyhow", working_dir, |command| command)
+        .assert()
+        .success();
+
+    let output = override_path("new-anyhow", working_dir, |command| {
+        command.arg("--replace")
+    })
+    .output()
+    .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 1.1.5 (local path) to 1.1.6 (local path)
+    "#);
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_that!(
+        manifest,
+        ends_with("[patch.crates-io]\nanyhow = { path = \"new-anyhow\" }\n")
+    );
+}
+
+#[googletest::test]
+fn patch_registry_that_replaces_crates_io() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    write_cargo_config(
+        working_dir,
+        r#"
+        [registries]
+        mirror = { index = "https://mirror.example.com/index" }
+
+        [source.crates-io]
+        replace-with = "mirror"
+
+        [source.mirror]
+        registry = "https://mirror.example.com/index"
+        "#,
+    );
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86").registry("mirror"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    // The mirror does not exist, so nothing must be fetched from it
+    let output = override_path("anyhow", working_dir, |command| command.arg("--offline"))
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "mirror"
+    "#);
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_that!(
+        manifest,
+        ends_with("[patch.mirror]\nanyhow = { path = \"anyhow\" }\n")
+    );
+}
+
+/// The index of a dependency is matched to a registry in the config even when they are spelled differently
+#[googletest::test]
+fn patch_registry_with_differently_spelled_index() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    write_cargo_config(
+        working_dir,
+        r#"
+        [registries]
+        private-registry = { index = "https://dl.cloudsmith.io/basic/private/registry/cargo/index.git" }
+
+        [source."registry+https://dl.cloudsmith.io/basic/private/registry/cargo/index.git"]
+        registry = "https://dl.cloudsmith.io/basic/private/registry/cargo/index.git"
+        replace-with = "vendored-sources"
+
+        [source.vendored-sources]
+        directory = "vendor"
+        "#,
+    );
+
+    {
+        let vendored_crate = working_dir.join("vendor").join("anyhow");
+
+        fs::create_dir_all(&vendored_crate).expect("failed to create vendored crate folder");
+
+        let manifest = Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render();
+
+        let _ = create_cargo_manifest(&vendored_crate, &manifest);
+        let checksum = Checksum::package_only_manifest(&manifest);
+        checksum.write_to_dir(&vendored_crate);
+    }
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(
+            Dependency::new("anyhow", "1.0.86")
+                .registry_index("https://dl.cloudsmith.io/basic/private/registry/cargo/index/"),
+        )
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let output = override_path("anyhow", working_dir, |command| command)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "private-registry"
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://dl.cloudsmith.io/basic/private/registry/cargo/index/) to 1.1.5 (local path)
+    "#);
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_that!(manifest, contains_substring("[patch.private-registry]"));
+}
+
 #[googletest::test]
 fn patch_transative() {
     let working_dir = tempfile::Builder::new().keep(true).tempdir().unwrap();
@@ -232,9 +506,10 @@ fn patch_transative() {
     assert.success();
 
     insta::assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
+    insta::assert_snapshot!(stderr, @r#"
     Patched dependency "anyhow" on registry "crates-io"
-    "###);
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+    "#);
 
     let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
 
@@ -298,9 +573,14 @@ fn patch_exists() {
     assert.success();
 
     insta::assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
-    Patched dependency "anyhow" on registry "crates-io"
-    "###);
+    insta::with_settings!({filters => vec![
+        LOCKED_VERSION,
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from [VERSION] (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+        "#);
+    });
 
     let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
 
@@ -326,6 +606,125 @@ fn patch_exists() {
     "###);
 }
 
+#[googletest::test]
+fn patch_dry_run() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let patch_crate_name = "anyhow";
+    let patch_folder = patch_crate_name.to_string();
+    let patch_folder_path = working_dir.join(patch_folder.clone());
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new(patch_crate_name, "1.0.86"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+    let _patch_manifest_path = create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic(patch_crate_name).version("1.1.5".to_owned()))
+            .add_target(Target::lib(patch_crate_name, "src/lib.rs"))
+            .render(),
+    );
+
+    let mut command = override_path(&patch_folder, working_dir, |command| {
+        command.arg("--dry-run")
+    });
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @r#"
+    --- Cargo.toml
+    +++ Cargo.toml
+    @@ -11,3 +11,6 @@
+     [[bin]]
+     name = "package-name"
+     path = "src/main.rs"
+    +
+    +[patch.crates-io]
+    +anyhow = { path = "anyhow" }
+    "#);
+    insta::assert_snapshot!(stderr, @r#"
+    Dry run: patch for dependency "anyhow" on registry "crates-io" was not written
+    "#);
+
+    let manifest_after = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest, manifest_after);
+    expect_that!(working_dir.join(CARGO_LOCK).exists(), eq(false));
+}
+
+/// Previewing a patch for a crate the project only depends on through another crate,
+/// which needs the dependency graph to be resolved
+#[googletest::test]
+fn patch_dry_run_transitive() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [
+            (
+                "foo",
+                Manifest::new(Header::basic("foo"))
+                    .add_dependency(Dependency::new("anyhow", "1.0.86")),
+            ),
+            (
+                "anyhow",
+                Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+            ),
+        ],
+    );
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("foo", "0.1.0"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let mut command = override_path("anyhow", working_dir, |command| command.arg("--dry-run"));
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stderr, @r#"
+    Dry run: patch for dependency "anyhow" on registry "crates-io" was not written
+    "#);
+
+    let manifest_after = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest, manifest_after);
+    expect_that!(working_dir.join(CARGO_LOCK).exists(), eq(false));
+}
+
 #[googletest::test]
 fn patch_uses_workspace_version_inheritance() {
     let working_dir = TempDir::new().unwrap();
@@ -384,9 +783,14 @@ fn patch_uses_workspace_version_inheritance() {
     assert.success();
 
     insta::assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
-    Patched dependency "anyhow" on registry "crates-io"
-    "###);
+    insta::with_settings!({filters => vec![
+        LOCKED_VERSION,
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from [VERSION] (registry+https://github.com/rust-lang/crates.io-index) to 1.0.87 (local path)
+        "#);
+    });
 
     let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
 
@@ -470,9 +874,14 @@ fn project_is_workspace() {
     assert.success();
 
     insta::assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
-    Patched dependency "anyhow" on registry "crates-io"
-    "###);
+    insta::with_settings!({filters => vec![
+        LOCKED_VERSION,
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from [VERSION] (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+        "#);
+    });
 
     let manifest = fs::read_to_string(workspace_folder_manifest_path).unwrap();
 
@@ -537,9 +946,14 @@ fn patch_manifest_in_subdir() {
     assert.success();
 
     insta::assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
-    Patched dependency "anyhow" on registry "crates-io"
-    "###);
+    insta::with_settings!({filters => vec![
+        LOCKED_VERSION,
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from [VERSION] (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+        "#);
+    });
 
     let manifest = fs::read_to_string(project_manifest_path).unwrap();
 
@@ -611,9 +1025,14 @@ fn patch_absolute_path() {
     assert.success();
 
     insta::assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
-    Patched dependency "anyhow" on registry "crates-io"
-    "###);
+    insta::with_settings!({filters => vec![
+        LOCKED_VERSION,
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from [VERSION] (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+        "#);
+    });
 
     let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
 
@@ -658,85 +1077,1233 @@ fn patch_version_incompatible_fails(dependency_version: &str, patch_version: &st
     let patch_folder = patch_crate_name.to_string();
     let patch_folder_path = working_dir.join(patch_folder.clone());
 
-    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new(patch_crate_name, dependency_version))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+    let _patch_manifest_path = create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(
+            Header::basic(patch_crate_name)
+                .name(patch_crate_name.to_owned())
+                .version(patch_version.to_owned()),
+        )
+        .add_target(Target::lib("patch_pacakge", "src/lib.rs"))
+        .render(),
+    );
+
+    let manifest_before = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    let mut command = override_path(&patch_folder, working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::allow_duplicates! {
+        insta::with_settings!({filters => vec![
+            (r#"redact = ".*""#, "[DEPENDENCY]"),
+            (r"\^+ requires `.*`", "[REQUIREMENT]"),
+            (r"has version .*\.", "has version [VERSION]."),
+        ]}, {
+            insta::assert_snapshot!(stdout, @"");
+            insta::assert_snapshot!(stderr, @r#"
+            error: patch could not be applied because version is incompatible
+             --> Cargo.toml:9:1
+              |
+            9 | [DEPENDENCY]
+              | [REQUIREMENT]
+              |
+              = help: the patch has version [VERSION]. Use `--force` to apply it anyway
+            "#
+            );
+        })
+    }
+
+    let manifest_after = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest_before, manifest_after);
+}
+
+#[googletest::test]
+fn patch_existing_patch_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let working_dir_manifest_path = create_existing_patch_project(working_dir);
+
+    let manifest_before = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    let mut command = override_path("new-anyhow", working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: "anyhow" is already patched with `{ path = "old-anyhow" }`
+      --> Cargo.toml:16:10
+       |
+    16 | anyhow = { path = "old-anyhow" }
+       |          ^^^^^^^^^^^^^^^^^^^^^^^ existing patch
+       |
+       = help: use `--replace` to replace it
+    "#);
+
+    let manifest_after = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest_before, manifest_after);
+}
+
+#[googletest::test]
+fn patch_existing_patch_replace_keeps_replaced() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let working_dir_manifest_path = create_existing_patch_project(working_dir);
+
+    let mut command = override_path("new-anyhow", working_dir, |command| {
+        command.args(["--replace", "--keep-replaced"])
+    });
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "crates-io"
+    "#);
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    insta::assert_toml_snapshot!(manifest, @r##"
+    '''
+    [package]
+    name = "package-name"
+    version = "0.1.0"
+    edition = "2021"
+
+    # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+    [dependencies]
+    anyhow = "1.0.86"
+
+    [[bin]]
+    name = "package-name"
+    path = "src/main.rs"
+
+    [patch.crates-io]
+    # anyhow = { path = "old-anyhow" }
+    anyhow = { path = "new-anyhow" }
+    '''
+    "##);
+}
+
+#[googletest::test]
+fn patch_existing_patch_replace_keeps_comments() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let working_dir_manifest_path = create_existing_patch_project(working_dir);
+
+    let manifest = fs::read_to_string(&working_dir_manifest_path)
+        .unwrap()
+        .replace(
+            "anyhow = {",
+            "# Needed until the fix is released\n# See the team wiki\nanyhow = {",
+        );
+    fs::write(&working_dir_manifest_path, manifest).unwrap();
+
+    let mut command = override_path("new-anyhow", working_dir, |command| {
+        command.arg("--replace")
+    });
+
+    command.assert().success();
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_that!(
+        manifest,
+        ends_with(
+            "[patch.crates-io]\n\
+             # Needed until the fix is released\n\
+             # See the team wiki\n\
+             anyhow = { path = \"new-anyhow\" }\n"
+        )
+    );
+}
+
+/// Creates a project that depends on `anyhow`, which is already patched with `old-anyhow`.
+/// Another version of `anyhow` is created in `new-anyhow`
+fn create_existing_patch_project(working_dir: &Path) -> PathBuf {
+    for folder in ["old-anyhow", "new-anyhow"] {
+        let patch_folder_path = working_dir.join(folder);
+
+        fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+        let _ = create_cargo_manifest(
+            &patch_folder_path,
+            &Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned()))
+                .add_target(Target::lib("anyhow", "src/lib.rs"))
+                .render(),
+        );
+    }
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    create_cargo_manifest(
+        working_dir,
+        &format!("{manifest}\n[patch.crates-io]\nanyhow = {{ path = \"old-anyhow\" }}\n"),
+    )
+}
+
+#[googletest::test]
+fn patch_version_incompatible_force_succeeds() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let patch_crate_name = "redact";
+
+    let patch_folder = patch_crate_name.to_string();
+    let patch_folder_path = working_dir.join(patch_folder.clone());
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new(patch_crate_name, "0.1.0"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+    let _patch_manifest_path = create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(
+            Header::basic(patch_crate_name)
+                .name(patch_crate_name.to_owned())
+                .version("0.0.2".to_owned()),
+        )
+        .add_target(Target::lib("patch_pacakge", "src/lib.rs"))
+        .render(),
+    );
+
+    let mut command = override_path(&patch_folder, working_dir, |command| command.arg("--force"));
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    warning: cargo does not use the patch for "redact". The patch was kept because `--force` was passed
+    Patched dependency "redact" on registry "crates-io"
+    "#);
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    insta::assert_toml_snapshot!(manifest, @r###"
+    '''
+    [package]
+    name = "package-name"
+    version = "0.1.0"
+    edition = "2021"
+
+    # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+    [dependencies]
+    redact = "0.1.0"
+
+    [[bin]]
+    name = "package-name"
+    path = "src/main.rs"
+
+    [patch.crates-io]
+    redact = { path = "redact" }
+    '''
+    "###);
+}
+
+#[test_case(None, None)]
+#[test_case(Some("anyhow"), None)]
+#[test_case(None, Some("0.1.0"))]
+#[googletest::test]
+fn missing_required_fields_on_patch(name: Option<&str>, version: Option<&str>) {
+    let patch_crate_name = name.unwrap_or("anyhow");
+
+    let [name, version] = [name, version].map(|option| option.map(str::to_owned));
+
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let patch_folder = patch_crate_name.to_string();
+    let patch_folder_path = working_dir.join(patch_folder.clone());
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new(patch_crate_name, "1.0.86"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+    let _patch_manifest_path = create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic(patch_crate_name).name(name).version(version)).render(),
+    );
+
+    let manifest_before = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    let mut command = override_path(&patch_folder, working_dir, |command| command);
+
+    let assert = command.assert();
+
+    assert.failure();
+
+    let manifest_after = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest_before, manifest_after);
+}
+
+/// When we add a patch we want to make sure that we're actually depending on the dependency we're
+/// patching.
+#[googletest::test]
+fn patch_incompatible_with_every_dependent_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let patch_crate_name = "anyhow";
+    let intermediary_crate_name = "foo";
+    let patch_folder = patch_crate_name.to_string();
+    let patch_folder_path = working_dir.join(patch_folder.clone());
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    vendor_crates(
+        working_dir,
+        [
+            (
+                intermediary_crate_name,
+                Manifest::new(Header::basic(intermediary_crate_name))
+                    .add_dependency(Dependency::new(patch_crate_name, "1.0.86")),
+            ),
+            (
+                patch_crate_name,
+                Manifest::new(Header::basic(patch_crate_name).version("1.0.86".to_owned())),
+            ),
+        ],
+    );
+
+    let package_name = "package_name";
+    let manifest_header = Header::basic(package_name);
+    let manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new(intermediary_crate_name, "0.1.0"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+    let _patch_manifest_path = create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic(patch_crate_name).version("2.0.0".to_owned()))
+            .add_target(Target::lib(patch_crate_name, "src/lib.rs"))
+            .render(),
+    );
+
+    let lock_path = working_dir.join(CARGO_LOCK);
+
+    let mut command = override_path(&patch_folder, working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: patch could not be applied because version 2.0.0 does not meet the requirement of any crate that depends on "anyhow": foo 0.1.0 (requires `^1.0.86`). Use `--force` to apply it anyway
+    "#);
+
+    let manifest_after = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest, manifest_after);
+
+    // `Cargo.lock` was created to check the patch, and did not exist before
+    expect_that!(lock_path.exists(), eq(false));
+}
+
+/// A patch can meet every requirement and still be left out by cargo,
+/// here because it links to the same native library as another crate
+#[googletest::test]
+fn patch_unused_by_cargo_is_rolled_back() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    let links_native = |dir: &Path, name: &str, version: &str| {
+        fs::create_dir(dir).expect("failed to create crate folder");
+        fs::write(dir.join("build.rs"), "fn main() {}").expect("failed to write build script");
+
+        create_cargo_manifest(
+            dir,
+            &format!(
+                "[package]\nname = \"{name}\"\nversion = \"{version}\"\nedition = \"2021\"\n\
+                 links = \"native\"\nbuild = \"build.rs\"\n\n[lib]\npath = \"src/lib.rs\"\n"
+            ),
+        );
+    };
+
+    links_native(&working_dir.join("anyhow"), "anyhow", "1.0.99");
+    links_native(&working_dir.join("native-sys"), "native-sys", "0.1.0");
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render()
+        .replace(
+            "[dependencies]\n",
+            "[dependencies]\nnative-sys = { path = \"native-sys\" }\n",
+        );
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = override_path("anyhow", working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: patch could not be applied because cargo does not use it. Check that version 1.0.99 of "anyhow" is compatible with every crate that depends on it
+    "#);
+
+    let manifest_after = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest, manifest_after);
+
+    // `Cargo.lock` did not exist before, so it is removed again
+    expect_that!(working_dir.join(CARGO_LOCK).exists(), eq(false));
+}
+
+/// Crates that require another version keep using the registry copy, so the project ends up with two
+#[googletest::test]
+fn patch_incompatible_with_transitive_dependency_warns() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let patch_folder_path = working_dir.join("anyhow");
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    vendor_crates(
+        working_dir,
+        [
+            (
+                "foo",
+                Manifest::new(Header::basic("foo"))
+                    .add_dependency(Dependency::new("anyhow", "0.9")),
+            ),
+            (
+                "anyhow",
+                Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+            ),
+        ],
+    );
+
+    {
+        let vendored_crate = working_dir.join("vendor").join("anyhow-0.9.0");
+
+        fs::create_dir(&vendored_crate).expect("failed to create vendored crate folder");
+
+        let manifest = Manifest::new(Header::basic("anyhow").version("0.9.0".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render();
+
+        let _ = create_cargo_manifest(&vendored_crate, &manifest);
+        let checksum = Checksum::package_only_manifest(&manifest);
+        checksum.write_to_dir(&vendored_crate);
+    }
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .add_dependency(Dependency::new("foo", "0.1.0"))
+        .render();
+
+    create_cargo_manifest(working_dir, &manifest);
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let output = override_path("anyhow", working_dir, |command| command)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stderr, @r#"
+    warning: foo 0.1.0 (requires `^0.9`) will keep using the original "anyhow", because version 1.1.5 of the patch does not meet their requirements. The project will have two copies of "anyhow"
+    Patched dependency "anyhow" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+    "#);
+}
+
+/// A fork that removed a feature the project enables would only fail once it is compiled
+#[googletest::test]
+fn patch_missing_feature_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(working_dir, []);
+
+    {
+        let vendored_crate = working_dir.join("vendor").join("anyhow");
+
+        fs::create_dir(&vendored_crate).expect("failed to create vendored crate folder");
+
+        let manifest = Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render()
+            + "\n[features]\nstd = []\n";
+
+        let _ = create_cargo_manifest(&vendored_crate, &manifest);
+        let checksum = Checksum::package_only_manifest(&manifest);
+        checksum.write_to_dir(&vendored_crate);
+    }
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .render()
+        + "\n[dependencies]\nanyhow = { version = \"1.0.86\", features = [\"std\"] }\n";
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let output = override_path("anyhow", working_dir, |command| command)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(false));
+
+    insta::assert_snapshot!(stderr, @r#"
+    error: patch could not be applied because it does not declare the feature `std` that the project enables on "anyhow". Use `--force` to apply it anyway
+    "#);
+
+    let manifest_after = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest, manifest_after);
+}
+
+/// A feature the project asks for is required of the patch, even when the original crate enables it by default
+#[googletest::test]
+fn patch_missing_feature_enabled_by_default_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(working_dir, []);
+    vendor_manifest(
+        working_dir,
+        "anyhow",
+        &(anyhow_manifest("1.0.86") + "\n[features]\ndefault = [\"std\"]\nstd = []\n"),
+    );
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .render()
+        + "\n[dependencies]\nanyhow = { version = \"1.0.86\", features = [\"std\"] }\n";
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_cargo_manifest(
+        &patch_folder_path,
+        &(anyhow_manifest("1.1.5") + "\n[features]\ndefault = []\n"),
+    );
+
+    let output = override_path("anyhow", working_dir, |command| command)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(false));
+
+    insta::assert_snapshot!(stderr, @r#"
+    error: patch could not be applied because it does not declare the feature `std` that the project enables on "anyhow". Use `--force` to apply it anyway
+    "#);
+
+    let manifest_after = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest, manifest_after);
+}
+
+/// Features are only checked once they are enabled, not when a dependency merely could enable them
+#[googletest::test]
+fn patch_missing_feature_not_enabled_succeeds() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(working_dir, []);
+    vendor_manifest(
+        working_dir,
+        "anyhow",
+        &(anyhow_manifest("1.0.86") + "\n[features]\nstd = []\n"),
+    );
+    vendor_manifest(
+        working_dir,
+        "foo",
+        &(Manifest::new(Header::basic("foo"))
+            .add_target(Target::lib("foo", "src/lib.rs"))
+            .add_dependency(Dependency::new("anyhow", "1.0.86"))
+            .render()
+            + "\n[features]\nstd = [\"anyhow/std\"]\n"),
+    );
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("foo", "0.1.0"))
+        .render();
+
+    create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let output = override_path("anyhow", working_dir, |command| command)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+    "#);
+}
+
+/// The features enabled on one version of a crate are not required of a patch for another version,
+/// even when both are depended on under different names
+#[googletest::test]
+fn patch_missing_feature_of_renamed_version_succeeds() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(working_dir, []);
+    vendor_manifest(
+        working_dir,
+        "anyhow-1",
+        &(anyhow_manifest("1.0.86") + "\n[features]\nstd = []\n"),
+    );
+    vendor_manifest(working_dir, "anyhow-2", &anyhow_manifest("2.0.0"));
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .render()
+        + "\n[dependencies]\n\
+           anyhow = { version = \"1.0.86\", features = [\"std\"] }\n\
+           anyhow2 = { package = \"anyhow\", version = \"2\" }\n";
+
+    create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("2.0.1".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let output = override_path("anyhow", working_dir, |command| command)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stderr, @r#"
+    warning: package_name 0.1.0 (requires `^1.0.86`) will keep using the original "anyhow", because version 2.0.1 of the patch does not meet their requirements. The project will have two copies of "anyhow"
+    Patched dependency "anyhow" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 2.0.0 (registry+https://github.com/rust-lang/crates.io-index) to 2.0.1 (local path)
+    "#);
+}
+
+/// Vendors a crate with its manifest written out in full, into a folder of its own,
+/// so that several versions of a crate can be vendored
+fn vendor_manifest(working_dir: &Path, folder: &str, manifest: &str) {
+    let vendored_crate = working_dir.join("vendor").join(folder);
+
+    fs::create_dir(&vendored_crate).expect("failed to create vendored crate folder");
+
+    let _ = create_cargo_manifest(&vendored_crate, manifest);
+    let checksum = Checksum::package_only_manifest(manifest);
+    checksum.write_to_dir(&vendored_crate);
+}
+
+fn anyhow_manifest(version: &str) -> String {
+    Manifest::new(Header::basic("anyhow").version(version.to_owned()))
+        .add_target(Target::lib("anyhow", "src/lib.rs"))
+        .render()
+}
+
+#[googletest::test]
+fn patch_path_workspace_picks_dependency() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let (manifest_path, output) =
+        override_path_workspace(working_dir, &["anyhow"], |command| command);
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+    "#);
+
+    let manifest = fs::read_to_string(manifest_path).unwrap();
+
+    insta::with_settings!({filters => vec![
+        (r#"[\"\']workspace[\/\\]anyhow[\"\']"#, "[PATH]"),
+    ]}, {
+        insta::assert_toml_snapshot!(manifest, @r##"
+        '''
+        [package]
+        name = "package-name"
+        version = "0.1.0"
+        edition = "2021"
+
+        # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+        [dependencies]
+        anyhow = "1.0.86"
+
+        [[bin]]
+        name = "package-name"
+        path = "src/main.rs"
+
+        [patch.crates-io]
+        anyhow = { path = [PATH] }
+        '''
+        "##);
+    });
+}
+
+/// Members left out of `default-members` can still be picked as the patch
+#[googletest::test]
+fn patch_path_workspace_picks_dependency_outside_default_members() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    let workspace_dir = working_dir.join("workspace");
+    create_workspace(&workspace_dir, &[("anyhow", "1.1.5"), ("redact", "0.1.11")]);
+
+    let mut workspace_manifest = File::options()
+        .append(true)
+        .open(workspace_dir.join(CARGO_TOML))
+        .unwrap();
+    writeln!(workspace_manifest, "default-members = [\"redact\"]").unwrap();
+
+    let package_name = "package-name";
+    create_cargo_manifest(
+        working_dir,
+        &Manifest::new(Header::basic(package_name))
+            .add_target(Target::bin(package_name, "src/main.rs"))
+            .add_dependency(Dependency::new("anyhow", "1.0.86"))
+            .render(),
+    );
+
+    let output = override_path("workspace", working_dir, |command| command)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+    "#);
+}
+
+#[googletest::test]
+fn patch_path_workspace_ambiguous_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let (_, output) =
+        override_path_workspace(working_dir, &["anyhow", "redact"], |command| command);
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(false));
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: multiple candidate packages found (anyhow, redact). Choose one with the `--package` flag
+    "#);
+}
+
+/// Piped input is not a terminal, so no choice is asked for
+#[googletest::test]
+fn patch_path_workspace_ambiguous_with_piped_stdin_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let (_, output) = override_path_workspace(working_dir, &["anyhow", "redact"], |command| {
+        command.write_stdin("1\n")
+    });
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(false));
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: multiple candidate packages found (anyhow, redact). Choose one with the `--package` flag
+    "#);
+}
+
+#[googletest::test]
+fn patch_path_workspace_ambiguous_json_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let (_, output) = override_path_workspace(working_dir, &["anyhow", "redact"], |command| {
+        command.args(["--message-format", "json"])
+    });
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(false));
+
+    insta::assert_snapshot!(stdout, @r#"
+    {"reason":"error","code":"multiple-packages","message":"multiple candidate packages found (anyhow, redact). Choose one with the `--package` flag","causes":[]}
+    "#);
+    insta::assert_snapshot!(stderr, @"");
+}
+
+/// Without a terminal to ask on, a crate used in several incompatible versions is patched without asking which one
+#[googletest::test]
+fn patch_transitive_in_several_versions_with_piped_stdin() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(working_dir, []);
+    vendor_manifest(working_dir, "anyhow-1", &anyhow_manifest("1.0.86"));
+    vendor_manifest(working_dir, "anyhow-2", &anyhow_manifest("2.0.0"));
+
+    for (name, requirement) in [("foo", "1.0.86"), ("bar", "2.0.0")] {
+        vendor_manifest(
+            working_dir,
+            name,
+            &Manifest::new(Header::basic(name))
+                .add_target(Target::lib(name, "src/lib.rs"))
+                .add_dependency(Dependency::new("anyhow", requirement))
+                .render(),
+        );
+    }
+
+    let package_name = "package_name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("foo", "0.1.0"))
+        .add_dependency(Dependency::new("bar", "0.1.0"))
+        .render();
+
+    create_cargo_manifest(working_dir, &manifest);
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("2.0.1".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let output = override_path("anyhow", working_dir, |command| command.write_stdin("2\n"))
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    warning: foo 0.1.0 (requires `^1.0.86`) will keep using the original "anyhow", because version 2.0.1 of the patch does not meet their requirements. The project will have two copies of "anyhow"
+    Patched dependency "anyhow" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 2.0.0 (registry+https://github.com/rust-lang/crates.io-index) to 2.0.1 (local path)
+    "#);
+}
+
+#[googletest::test]
+fn patch_path_workspace_with_package() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let (manifest_path, output) =
+        override_path_workspace(working_dir, &["anyhow", "redact"], |command| {
+            command.args(["--package", "redact"])
+        });
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "redact" on registry "crates-io"
+    Updated "redact" in `Cargo.lock` from 0.1.10 (registry+https://github.com/rust-lang/crates.io-index) to 0.1.11 (local path)
+    "#);
+
+    let manifest = fs::read_to_string(manifest_path).unwrap();
+
+    insta::with_settings!({filters => vec![
+        (r#"[\"\']workspace[\/\\]redact[\"\']"#, "[PATH]"),
+    ]}, {
+        insta::assert_toml_snapshot!(manifest, @r##"
+        '''
+        [package]
+        name = "package-name"
+        version = "0.1.0"
+        edition = "2021"
+
+        # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+        [dependencies]
+        anyhow = "1.0.86"
+        redact = "0.1.10"
+
+        [[bin]]
+        name = "package-name"
+        path = "src/main.rs"
+
+        [patch.crates-io]
+        redact = { path = [PATH] }
+        '''
+        "##);
+    });
+}
+
+#[googletest::test]
+fn patch_path_workspace_all() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let (manifest_path, output) =
+        override_path_workspace(working_dir, &["anyhow", "redact"], |command| {
+            command.arg("--all")
+        });
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "crates-io"
+    Patched dependency "redact" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+    Updated "redact" in `Cargo.lock` from 0.1.10 (registry+https://github.com/rust-lang/crates.io-index) to 0.1.11 (local path)
+    "#);
+
+    let manifest = fs::read_to_string(manifest_path).unwrap();
+
+    insta::with_settings!({filters => vec![
+        (r#"[\"\']workspace[\/\\]anyhow[\"\']"#, "[ANYHOW_PATH]"),
+        (r#"[\"\']workspace[\/\\]redact[\"\']"#, "[REDACT_PATH]"),
+    ]}, {
+        insta::assert_toml_snapshot!(manifest, @r##"
+        '''
+        [package]
+        name = "package-name"
+        version = "0.1.0"
+        edition = "2021"
+
+        # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+        [dependencies]
+        anyhow = "1.0.86"
+        redact = "0.1.10"
+
+        [[bin]]
+        name = "package-name"
+        path = "src/main.rs"
+
+        [patch.crates-io]
+        anyhow = { path = [ANYHOW_PATH] }
+        redact = { path = [REDACT_PATH] }
+        '''
+        "##);
+    });
+}
+
+#[googletest::test]
+fn patch_path_workspace_all_skips_unused_members() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let (manifest_path, output) =
+        override_path_workspace(working_dir, &["redact"], |command| command.arg("--all"));
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "redact" on registry "crates-io"
+    Updated "redact" in `Cargo.lock` from 0.1.10 (registry+https://github.com/rust-lang/crates.io-index) to 0.1.11 (local path)
+    "#);
+
+    let manifest = fs::read_to_string(manifest_path).unwrap();
+
+    expect_that!(manifest, not(contains_substring("anyhow")));
+}
+
+#[googletest::test]
+fn patch_path_workspace_offline() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let (_, output) = override_path_workspace(working_dir, &["redact"], |command| {
+        command.args(["--all", "--offline"])
+    });
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "redact" on registry "crates-io"
+    Updated "redact" in `Cargo.lock` from 0.1.10 (registry+https://github.com/rust-lang/crates.io-index) to 0.1.11 (local path), using only crates available offline
+    "#);
+}
+
+#[googletest::test]
+fn patch_path_offline_with_missing_crate() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    vendor_crates(
+        working_dir,
+        [(
+            "anyhow",
+            Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+        )],
+    );
+
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).unwrap();
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.0.87".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
 
+    // The project also depends on a crate that has not been downloaded
     let package_name = "package-name";
-    let manifest_header = Header::basic(package_name);
-    let manifest = Manifest::new(manifest_header)
+    let manifest = Manifest::new(Header::basic(package_name))
         .add_target(Target::bin(package_name, "src/main.rs"))
-        .add_dependency(Dependency::new(patch_crate_name, dependency_version))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .add_dependency(Dependency::new("itoa", "1"))
         .render();
 
-    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
-    let _patch_manifest_path = create_cargo_manifest(
-        &patch_folder_path,
-        &Manifest::new(
-            Header::basic(patch_crate_name)
-                .name(patch_crate_name.to_owned())
-                .version(patch_version.to_owned()),
-        )
-        .add_target(Target::lib("patch_pacakge", "src/lib.rs"))
-        .render(),
-    );
-
-    let manifest_before = fs::read_to_string(&working_dir_manifest_path).unwrap();
+    create_cargo_manifest(working_dir, &manifest);
 
-    let mut command = override_path(&patch_folder, working_dir, |command| command);
+    let mut command = override_path("anyhow", working_dir, |command| command.arg("--offline"));
 
     let assert = command.assert();
 
     let output = assert.get_output();
 
-    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
     let stderr = String::from_utf8(output.stderr.clone()).unwrap();
 
-    assert.failure();
+    assert.success();
 
-    insta::allow_duplicates! {
-        insta::assert_snapshot!(stdout, @"");
-        insta::assert_snapshot!(stderr, @r###"
-        error: patch could not be applied because version is incompatible
-        "###
-        );
+    insta::with_settings!({filters => vec![
+        (r"(?s)(could not be resolved without accessing the network): .*?(`--offline` prevents)", "$1: [ERROR]. $2"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        warning: unable to verify that cargo uses the patch, because dependencies could not be resolved without accessing the network: [ERROR]. `--offline` prevents crates that are not downloaded yet from being fetched. Run `cargo update -p anyhow` without `--offline` to fetch them
+        Patched dependency "anyhow" on registry "crates-io"
+        "#);
+    });
+}
+
+/// Overrides with a local workspace that contains both `anyhow` and `redact`,
+/// from a project that depends on `dependencies`
+fn override_path_workspace(
+    working_dir: &Path,
+    dependencies: &[&str],
+    args: impl Fn(&mut Command) -> &mut Command,
+) -> (PathBuf, std::process::Output) {
+    let versions = [("anyhow", "1.0.86"), ("redact", "0.1.10")];
+
+    vendor_crates(
+        working_dir,
+        versions
+            .into_iter()
+            .filter(|(name, _)| dependencies.contains(name))
+            .map(|(name, version)| {
+                (
+                    name,
+                    Manifest::new(Header::basic(name).version(version.to_owned())),
+                )
+            }),
+    );
+
+    create_workspace(
+        &working_dir.join("workspace"),
+        &[("anyhow", "1.1.5"), ("redact", "0.1.11")],
+    );
+
+    let package_name = "package-name";
+    let mut manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"));
+
+    for (name, version) in versions {
+        if dependencies.contains(&name) {
+            manifest = manifest.add_dependency(Dependency::new(name, version));
+        }
     }
 
-    let manifest_after = fs::read_to_string(working_dir_manifest_path).unwrap();
+    let manifest_path = create_cargo_manifest(working_dir, &manifest.render());
 
-    expect_eq!(manifest_before, manifest_after);
+    let output = override_path("workspace", working_dir, args)
+        .output()
+        .unwrap();
+
+    (manifest_path, output)
 }
 
 #[googletest::test]
-fn patch_version_incompatible_force_succeeds() {
+fn patch_with_registry_version() {
     let working_dir = TempDir::new().unwrap();
     let working_dir = working_dir.path();
 
-    let patch_crate_name = "redact";
-
-    let patch_folder = patch_crate_name.to_string();
-    let patch_folder_path = working_dir.join(patch_folder.clone());
-
-    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
-
     let package_name = "package-name";
-    let manifest_header = Header::basic(package_name);
-    let manifest = Manifest::new(manifest_header)
+    let manifest = Manifest::new(Header::basic(package_name))
         .add_target(Target::bin(package_name, "src/main.rs"))
-        .add_dependency(Dependency::new(patch_crate_name, "0.1.0"))
+        .add_dependency(Dependency::new("anyhow", "1.0.80"))
         .render();
 
     let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
-    let _patch_manifest_path = create_cargo_manifest(
-        &patch_folder_path,
-        &Manifest::new(
-            Header::basic(patch_crate_name)
-                .name(patch_crate_name.to_owned())
-                .version("0.0.2".to_owned()),
-        )
-        .add_target(Target::lib("patch_pacakge", "src/lib.rs"))
-        .render(),
-    );
 
-    let mut command = override_path(&patch_folder, working_dir, |command| command.arg("--force"));
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args([
+            "override",
+            "--package",
+            "anyhow",
+            "--registry-version",
+            "1.0.82",
+        ])
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
 
     let assert = command.assert();
 
@@ -748,13 +2315,18 @@ fn patch_version_incompatible_force_succeeds() {
     assert.success();
 
     insta::assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
-    Patched dependency "redact" on registry "crates-io"
-    "###);
+    insta::with_settings!({filters => vec![
+        LOCKED_VERSION,
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from [VERSION] (registry+https://github.com/rust-lang/crates.io-index) to 1.0.82 (sparse+https://index.crates.io/)
+        "#);
+    });
 
     let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
 
-    insta::assert_toml_snapshot!(manifest, @r###"
+    insta::assert_toml_snapshot!(manifest, @r##"
     '''
     [package]
     name = "package-name"
@@ -764,63 +2336,75 @@ fn patch_version_incompatible_force_succeeds() {
     # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
 
     [dependencies]
-    redact = "0.1.0"
+    anyhow = "1.0.80"
 
     [[bin]]
     name = "package-name"
     path = "src/main.rs"
 
     [patch.crates-io]
-    redact = { path = "redact" }
+    anyhow = { version = "=1.0.82", registry-index = "sparse+https://index.crates.io/" }
     '''
-    "###);
-}
+    "##);
 
-#[test_case(None, None)]
-#[test_case(Some("anyhow"), None)]
-#[test_case(None, Some("0.1.0"))]
-#[googletest::test]
-fn missing_required_fields_on_patch(name: Option<&str>, version: Option<&str>) {
-    let patch_crate_name = name.unwrap_or("anyhow");
+    let lock = fs::read_to_string(working_dir.join(CARGO_LOCK)).unwrap();
 
-    let [name, version] = [name, version].map(|option| option.map(str::to_owned));
+    expect_that!(
+        lock,
+        contains_substring(
+            "name = \"anyhow\"\nversion = \"1.0.82\"\nsource = \"sparse+https://index.crates.io/\""
+        )
+    );
+}
 
+#[googletest::test]
+fn patch_with_registry_version_requirement() {
     let working_dir = TempDir::new().unwrap();
     let working_dir = working_dir.path();
 
-    let patch_folder = patch_crate_name.to_string();
-    let patch_folder_path = working_dir.join(patch_folder.clone());
-
-    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
-
     let package_name = "package-name";
-    let manifest_header = Header::basic(package_name);
-    let manifest = Manifest::new(manifest_header)
+    let manifest = Manifest::new(Header::basic(package_name))
         .add_target(Target::bin(package_name, "src/main.rs"))
-        .add_dependency(Dependency::new(patch_crate_name, "1.0.86"))
+        .add_dependency(Dependency::new("anyhow", "1.0.80"))
         .render();
 
     let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
-    let _patch_manifest_path = create_cargo_manifest(
-        &patch_folder_path,
-        &Manifest::new(Header::basic(patch_crate_name).name(name).version(version)).render(),
-    );
-
-    let manifest_before = fs::read_to_string(&working_dir_manifest_path).unwrap();
 
-    let mut command = override_path(&patch_folder, working_dir, |command| command);
+    let mut command = Command::cargo_bin("cargo-override").unwrap();
+    command
+        .current_dir(working_dir)
+        .args(["override", "--package", "anyhow"])
+        .args(["--registry-version", ">=1.0.81, <1.0.83"])
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
 
     let assert = command.assert();
 
-    assert.failure();
+    let output = assert.get_output();
 
-    let manifest_after = fs::read_to_string(working_dir_manifest_path).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
 
-    expect_eq!(manifest_before, manifest_after);
+    assert.success();
+
+    insta::with_settings!({filters => vec![
+        LOCKED_VERSION,
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        Patched dependency "anyhow" on registry "crates-io"
+        Updated "anyhow" in `Cargo.lock` from [VERSION] (registry+https://github.com/rust-lang/crates.io-index) to 1.0.82 (sparse+https://index.crates.io/)
+        "#);
+    });
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_that!(
+        manifest,
+        contains_substring(
+            r#"anyhow = { version = "=1.0.82", registry-index = "sparse+https://index.crates.io/" }"#
+        )
+    );
 }
 
-/// When we add a patch we want to make sure that we're actually depending on the dependency we're
-/// patching.
 #[googletest::test]
 fn patch_exists_put_project_does_not_depend_on_it() {
     let working_dir = TempDir::new().unwrap();
@@ -997,7 +2581,6 @@ fn basic_cargo_config(path: &Path) {
     )
 }
 
-#[cfg(feature = "failing_tests")]
 fn basic_cargo_env_config(path: &Path) {
     write_cargo_config(
         path,
@@ -1008,7 +2591,7 @@ fn basic_cargo_env_config(path: &Path) {
     )
 }
 
-#[cfg_attr(feature = "failing_tests", test_case(basic_cargo_env_config))]
+#[test_case(basic_cargo_env_config)]
 #[test_case(basic_cargo_config)]
 #[googletest::test]
 fn patch_exists_alt_registry(setup: impl Fn(&Path)) {
@@ -1053,9 +2636,14 @@ fn patch_exists_alt_registry(setup: impl Fn(&Path)) {
 
     insta::allow_duplicates! {
         insta::assert_snapshot!(stdout, @"");
-        insta::assert_snapshot!(stderr, @r###"
-        Patched dependency "anyhow" on registry "private-registry"
-        "###);
+        insta::with_settings!({filters => vec![
+            (r"(?s)(could not be resolved): .*?\n(Patched)", "$1: [ERROR]\n$2"),
+        ]}, {
+            insta::assert_snapshot!(stderr, @r#"
+            warning: unable to verify that cargo uses the patch, because dependencies could not be resolved: [ERROR]
+            Patched dependency "anyhow" on registry "private-registry"
+            "#);
+        });
     }
 
     let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
@@ -1084,7 +2672,144 @@ fn patch_exists_alt_registry(setup: impl Fn(&Path)) {
     }
 }
 
-#[cfg_attr(feature = "failing_tests", test_case(basic_cargo_env_config))]
+#[googletest::test]
+fn patch_unnamed_registry_by_index() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let index = "https://dl.cloudsmith.io/basic/private/registry/cargo/index.git";
+
+    let patch_folder_path = working_dir.join("anyhow");
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86").registry_index(index))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let output = override_path("anyhow", working_dir, |command| command)
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(false));
+
+    insta::assert_snapshot!(stderr, @r#"
+    error: unable to determine registry name for `https://dl.cloudsmith.io/basic/private/registry/cargo/index.git`
+                     provide it using the `--registry` flag, or patch it by index URL with `--index`
+    "#);
+
+    let output = override_path("anyhow", working_dir, |command| {
+        command.args(["--index", index])
+    })
+    .output()
+    .unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::with_settings!({filters => vec![
+        (r"(?s)(could not be resolved): .*?\n(Patched)", "$1: [ERROR]\n$2"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        warning: unable to verify that cargo uses the patch, because dependencies could not be resolved: [ERROR]
+        Patched dependency "anyhow" on source "https://dl.cloudsmith.io/basic/private/registry/cargo/index.git"
+        "#);
+    });
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_that!(manifest, contains_substring(format!("[patch.\"{index}\"]")));
+}
+
+/// Defines the registry in the cargo home directory, rather than in the project
+fn cargo_home_config(working_dir: &Path, command: &mut Command) {
+    let cargo_home = working_dir.join("cargo-home");
+
+    fs::create_dir(&cargo_home).expect("failed to create cargo home folder");
+    fs::write(
+        cargo_home.join("config.toml"),
+        r#"
+        [registries]
+        private-registry = { index = "https://dl.cloudsmith.io/basic/private/registry/cargo/index.git" }
+        "#,
+    )
+    .expect("failed to write `config.toml`");
+
+    command.env("CARGO_HOME", cargo_home);
+}
+
+/// Defines the registry with `--config`
+fn cli_config(_: &Path, command: &mut Command) {
+    command.args([
+        "--config",
+        r#"registries.private-registry.index="https://dl.cloudsmith.io/basic/private/registry/cargo/index.git""#,
+    ]);
+}
+
+#[test_case(cargo_home_config)]
+#[test_case(cli_config)]
+#[googletest::test]
+fn patch_alt_registry_from_config_layers(setup: impl Fn(&Path, &mut Command)) {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let patch_folder_path = working_dir.join("anyhow");
+
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86").registry("private-registry"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+    create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.1.5".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let mut command = override_path("anyhow", working_dir, |command| command);
+    setup(working_dir, &mut command);
+
+    let output = command.output().unwrap();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    expect_that!(output.status.success(), eq(true));
+
+    insta::allow_duplicates! {
+        insta::with_settings!({filters => vec![
+            (r"(?s)(could not be resolved): .*?\n(Patched)", "$1: [ERROR]\n$2"),
+        ]}, {
+            insta::assert_snapshot!(stderr, @r#"
+            warning: unable to verify that cargo uses the patch, because dependencies could not be resolved: [ERROR]
+            Patched dependency "anyhow" on registry "private-registry"
+            "#);
+        });
+    }
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_that!(manifest, contains_substring("[patch.private-registry]"));
+}
+
+#[test_case(basic_cargo_env_config)]
 #[test_case(basic_cargo_config)]
 #[googletest::test]
 fn patch_registry_mismatch_fails(setup: impl Fn(&Path)) {
@@ -1135,10 +2860,15 @@ fn patch_registry_mismatch_fails(setup: impl Fn(&Path)) {
             (patch_folder.as_str(), "[PATCH]"),
         ]}, {
             insta::assert_snapshot!(stdout, @"");
-            insta::assert_snapshot!(stderr, @r###"
+            insta::assert_snapshot!(stderr, @r#"
             error: user provided registry `another-registry` with the `--registry` flag but dependency `[PATCH]` uses registry `private-registry`. 
                                  To use the registry, you passed, use `--force`
-            "###);
+             --> Cargo.toml:9:1
+              |
+            9 | [PATCH] = { version = "1.0.86", registry = "private-registry" }
+              | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ uses registry `private-registry`
+              |
+            "#);
         })
     };
 
@@ -1147,7 +2877,7 @@ fn patch_registry_mismatch_fails(setup: impl Fn(&Path)) {
     expect_eq!(manifest_before, manifest_after);
 }
 
-#[cfg_attr(feature = "failing_tests", test_case(basic_cargo_env_config))]
+#[test_case(basic_cargo_env_config)]
 #[test_case(basic_cargo_config)]
 #[googletest::test]
 fn patch_registry_mismatch_force_succeeds(setup: impl Fn(&Path)) {
@@ -1196,9 +2926,14 @@ fn patch_registry_mismatch_force_succeeds(setup: impl Fn(&Path)) {
 
     insta::allow_duplicates! {
         insta::assert_snapshot!(stdout, @"");
-        insta::assert_snapshot!(stderr, @r###"
-        Patched dependency "anyhow" on registry "another-registry"
-        "###);
+        insta::with_settings!({filters => vec![
+            (r"(?s)(could not be resolved): .*?\n(Patched)", "$1: [ERROR]\n$2"),
+        ]}, {
+            insta::assert_snapshot!(stderr, @r#"
+            warning: unable to verify that cargo uses the patch, because dependencies could not be resolved: [ERROR]
+            Patched dependency "anyhow" on registry "another-registry"
+            "#);
+        });
     }
 
     let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
@@ -1270,9 +3005,14 @@ fn patch_exists_alt_registry_from_env() {
     assert.success();
 
     insta::assert_snapshot!(stdout, @"");
-    insta::assert_snapshot!(stderr, @r###"
-    Patched dependency "anyhow" on registry "private-registry"
-    "###);
+    insta::with_settings!({filters => vec![
+        (r"(?s)(could not be resolved): .*?\n(Patched)", "$1: [ERROR]\n$2"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        warning: unable to verify that cargo uses the patch, because dependencies could not be resolved: [ERROR]
+        Patched dependency "anyhow" on registry "private-registry"
+        "#);
+    });
 
     let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
 
@@ -1310,6 +3050,67 @@ fn write_cargo_config(path: &Path, toml: &str) {
     fs::write(&cargo_config, toml).expect("failed to write `.cargo/config.toml`");
 }
 
+/// Replaces crates.io with a directory of vendored crates, each containing nothing more than a manifest
+fn vendor_crates<'a>(working_dir: &Path, crates: impl IntoIterator<Item = (&'a str, Manifest)>) {
+    write_cargo_config(
+        working_dir,
+        r#"
+        [source.crates-io]
+        replace-with = "vendored-sources"
+
+        [source.vendored-sources]
+        directory = "vendor"
+        "#,
+    );
+
+    let vendor_dir = working_dir.join("vendor");
+
+    fs::create_dir(&vendor_dir).expect("failed to create vendor folder");
+
+    for (name, manifest) in crates {
+        let vendored_crate = vendor_dir.join(name);
+
+        fs::create_dir(&vendored_crate).expect("failed to create vendored crate folder");
+
+        let manifest = manifest
+            .add_target(Target::lib(name, "src/lib.rs"))
+            .render();
+
+        let _ = create_cargo_manifest(&vendored_crate, &manifest);
+        let checksum = Checksum::package_only_manifest(&manifest);
+        checksum.write_to_dir(&vendored_crate);
+    }
+}
+
+/// Creates a virtual workspace in `dir`, with a library crate for each `(name, version)` member
+fn create_workspace(dir: &Path, members: &[(&str, &str)]) {
+    let member_list = members
+        .iter()
+        .map(|(name, _)| format!("\"{name}\""))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    fs::create_dir_all(dir).expect("failed to create workspace folder");
+
+    let _ = create_cargo_manifest(
+        dir,
+        &format!("[workspace]\nmembers = [{member_list}]\nresolver = \"2\"\n"),
+    );
+
+    for (name, version) in members {
+        let member_dir = dir.join(name);
+
+        fs::create_dir_all(&member_dir).expect("failed to create member folder");
+
+        let _ = create_cargo_manifest(
+            &member_dir,
+            &Manifest::new(Header::basic(name).version(version.to_string()))
+                .add_target(Target::lib(name, "src/lib.rs"))
+                .render(),
+        );
+    }
+}
+
 fn create_cargo_manifest(dir: &Path, content: &str) -> PathBuf {
     let manifest_path = dir.join(CARGO_TOML);
     let mut manifest = File::create_new(&manifest_path).expect("failed to create manifest file");
@@ -179,6 +179,7 @@ pub struct Dependency {
     version: String,
     registry: Option<String>,
     registry_index: Option<String>,
+    git: Option<String>,
 }
 
 impl Dependency {
@@ -188,6 +189,7 @@ impl Dependency {
             version: version.as_ref().to_owned(),
             registry: None,
             registry_index: None,
+            git: None,
         }
     }
 
@@ -201,13 +203,31 @@ impl Dependency {
         self
     }
 
+    pub fn git(mut self, url: impl ToString) -> Dependency {
+        self.git = Some(url.to_string());
+        self
+    }
+
     fn render(self) -> String {
         match self {
+            Self {
+                name,
+                version,
+                registry: None,
+                registry_index: None,
+                git: Some(git),
+            } => {
+                format!("{name} = {{ version = \"{version}\", git = \"{git}\" }}")
+            }
+            Self { git: Some(_), .. } => {
+                unimplemented!("cannot set git with a registry")
+            }
             Self {
                 name,
                 version,
                 registry: Some(registry),
                 registry_index: None,
+                git: None,
             } => {
                 format!("{name} = {{ version = \"{version}\", registry = \"{registry}\" }}")
             }
@@ -216,6 +236,7 @@ impl Dependency {
                 version,
                 registry: None,
                 registry_index: Some(registry),
+                git: None,
             } => {
                 format!("{name} = {{ version = \"{version}\", registry-index = \"{registry}\" }}")
             }
@@ -224,6 +245,7 @@ impl Dependency {
                 version,
                 registry: None,
                 registry_index: None,
+                git: None,
             } => {
                 format!("{name} = \"{version}\"")
             }
@@ -232,6 +254,7 @@ impl Dependency {
                 version: _,
                 registry: Some(_),
                 registry_index: Some(_),
+                git: None,
             } => {
                 unimplemented!("cannot set bot registry and registry-index")
             }
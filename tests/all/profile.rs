@@ -0,0 +1,170 @@
+//! Tests involving `cargo override profile`
+
+use super::manifest::{Dependency, Header, Manifest, Target};
+use super::{create_cargo_manifest, create_workspace, vendor_crates};
+
+use std::path::Path;
+
+use assert_cmd::Command;
+use fs_err as fs;
+use googletest::{expect_eq, verify_eq, verify_that};
+use tempfile::TempDir;
+
+#[googletest::test]
+fn apply_and_remove_profile() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let project_dir = create_profile_project(working_dir);
+
+    let manifest_path = project_dir.join("Cargo.toml");
+    let manifest_before = fs::read_to_string(&manifest_path).unwrap();
+
+    let mut command = profile(&project_dir, &["apply", "local-dev"]);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    Patched dependency "anyhow" on registry "crates-io"
+    Patched dependency "redact" on registry "crates-io"
+    Updated "anyhow" in `Cargo.lock` from 1.0.86 (registry+https://github.com/rust-lang/crates.io-index) to 1.1.5 (local path)
+    Updated "redact" in `Cargo.lock` from 0.1.10 (registry+https://github.com/rust-lang/crates.io-index) to 0.1.11 (local path)
+    "#);
+
+    let manifest = fs::read_to_string(&manifest_path).unwrap();
+
+    insta::assert_toml_snapshot!(manifest, @r##"
+    '''
+    [package]
+    name = "package-name"
+    version = "0.1.0"
+    edition = "2021"
+
+    # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+    [dependencies]
+    anyhow = "1.0.86"
+    redact = "0.1.10"
+
+    [[bin]]
+    name = "package-name"
+    path = "src/main.rs"
+
+    [patch.crates-io]
+    anyhow = { path = "../upstream/anyhow" }
+    redact = { path = "../upstream/redact" }
+
+    [package.metadata.cargo-override.profiles.local-dev]
+    path = "../upstream"
+    crates = ["anyhow", "redact"]
+    '''
+    "##);
+
+    let mut command = profile(&project_dir, &["remove", "local-dev"]);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stderr, @r#"
+    Removed patch for dependency "anyhow" on registry "crates-io"
+    Removed patch for dependency "redact" on registry "crates-io"
+    "#);
+
+    let manifest_after = fs::read_to_string(&manifest_path).unwrap();
+
+    expect_eq!(manifest_before, manifest_after);
+}
+
+#[googletest::test]
+fn apply_missing_profile_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let project_dir = create_profile_project(working_dir);
+
+    let mut command = profile(&project_dir, &["apply", "missing"]);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: no profile named "missing" found in `[package.metadata.cargo-override.profiles]`
+    "#);
+}
+
+/// Creates a project in `project` that depends on `anyhow` and `redact`, with a profile that
+/// patches both from the workspace in `upstream`
+fn create_profile_project(working_dir: &Path) -> std::path::PathBuf {
+    let project_dir = working_dir.join("project");
+    fs::create_dir(&project_dir).expect("failed to create project folder");
+
+    vendor_crates(
+        &project_dir,
+        [
+            (
+                "anyhow",
+                Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned())),
+            ),
+            (
+                "redact",
+                Manifest::new(Header::basic("redact").version("0.1.10".to_owned())),
+            ),
+        ],
+    );
+
+    create_workspace(
+        &working_dir.join("upstream"),
+        &[("anyhow", "1.1.5"), ("redact", "0.1.11")],
+    );
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .add_dependency(Dependency::new("redact", "0.1.10"))
+        .render();
+
+    let _ = create_cargo_manifest(
+        &project_dir,
+        &format!(
+            r#"{manifest}
+[package.metadata.cargo-override.profiles.local-dev]
+path = "../upstream"
+crates = ["anyhow", "redact"]
+"#
+        ),
+    );
+
+    project_dir
+}
+
+fn profile(working_dir: &Path, args: &[&str]) -> Command {
+    let mut cmd = Command::cargo_bin("cargo-override").unwrap();
+    cmd.current_dir(working_dir)
+        .args(["override", "profile"])
+        .args(args)
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
+
+    cmd
+}
@@ -0,0 +1,279 @@
+//! Tests involving `cargo override remove`
+
+use super::manifest::{Dependency, Header, Manifest, Target};
+use super::{create_cargo_manifest, write_cargo_config};
+
+use std::path::Path;
+
+use assert_cmd::Command;
+use fs_err as fs;
+use googletest::{expect_eq, verify_eq, verify_that};
+use tempfile::TempDir;
+
+#[googletest::test]
+fn remove_patch() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let mut manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    manifest.push_str(
+        r#"
+[patch.crates-io]
+anyhow = { path = "anyhow" }
+"#,
+    );
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = remove_override("anyhow", working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    Removed patch for dependency "anyhow" on registry "crates-io"
+    "#);
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    insta::assert_toml_snapshot!(manifest, @r##"
+    '''
+    [package]
+    name = "package-name"
+    version = "0.1.0"
+    edition = "2021"
+
+    # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+    [dependencies]
+    anyhow = "1.0.86"
+
+    [[bin]]
+    name = "package-name"
+    path = "src/main.rs"
+    '''
+    "##);
+}
+
+#[googletest::test]
+fn remove_patch_keeps_other_patches() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let mut manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .add_dependency(Dependency::new("redact", "0.1.10"))
+        .render();
+
+    manifest.push_str(
+        r#"
+[patch.crates-io]
+# Local copy of anyhow
+anyhow = { path = "anyhow" }
+redact = { git = "https://github.com/eopb/redact" }
+"#,
+    );
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = remove_override("redact", working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    Removed patch for dependency "redact" on registry "crates-io"
+    "#);
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    insta::assert_toml_snapshot!(manifest, @r##"
+    '''
+    [package]
+    name = "package-name"
+    version = "0.1.0"
+    edition = "2021"
+
+    # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+    [dependencies]
+    anyhow = "1.0.86"
+    redact = "0.1.10"
+
+    [[bin]]
+    name = "package-name"
+    path = "src/main.rs"
+
+    [patch.crates-io]
+    # Local copy of anyhow
+    anyhow = { path = "anyhow" }
+    '''
+    "##);
+}
+
+#[googletest::test]
+fn remove_patch_on_multiple_registries_requires_registry() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    write_cargo_config(
+        working_dir,
+        r#"
+        [registries]
+        private-registry = { index = "https://dl.cloudsmith.io/basic/private/registry/cargo/index.git" }
+        "#,
+    );
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let mut manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .render();
+
+    manifest.push_str(
+        r#"
+[patch.crates-io]
+anyhow = { path = "anyhow" }
+
+[patch.private-registry]
+anyhow = { path = "anyhow" }
+"#,
+    );
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let manifest_before = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    let mut command = remove_override("anyhow", working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: "anyhow" is patched on multiple registries (crates-io, private-registry). Choose one with the `--registry` flag
+    "#);
+
+    let manifest_after = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest_before, manifest_after);
+
+    let mut command = remove_override("anyhow", working_dir, |command| {
+        command.arg("--registry").arg("private-registry")
+    });
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stderr, @r#"
+    Removed patch for dependency "anyhow" on registry "private-registry"
+    "#);
+
+    let manifest = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    insta::assert_toml_snapshot!(manifest, @r##"
+    '''
+    [package]
+    name = "package-name"
+    version = "0.1.0"
+    edition = "2021"
+
+    # See more keys and their definitions at https://doc.rust-lang.org/cargo/reference/manifest.html
+
+
+    [[bin]]
+    name = "package-name"
+    path = "src/main.rs"
+
+    [patch.crates-io]
+    anyhow = { path = "anyhow" }
+    '''
+    "##);
+}
+
+#[googletest::test]
+fn remove_missing_patch_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let package_name = "package-name";
+    let manifest_header = Header::basic(package_name);
+    let manifest = Manifest::new(manifest_header)
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    let working_dir_manifest_path = create_cargo_manifest(working_dir, &manifest);
+
+    let mut command = remove_override("anyhow", working_dir, |command| command);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    error: no patch found for "anyhow"
+    "#);
+
+    let manifest_after = fs::read_to_string(working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest, manifest_after);
+}
+
+fn remove_override(
+    name: &str,
+    working_dir: &Path,
+    args: impl Fn(&mut Command) -> &mut Command,
+) -> Command {
+    let mut cmd = Command::cargo_bin("cargo-override").unwrap();
+    args(
+        cmd.current_dir(working_dir)
+            .arg("override")
+            .arg("remove")
+            .arg(name),
+    )
+    .env("CARGO_HOME", working_dir)
+    .env_remove("RUST_BACKTRACE");
+
+    cmd
+}
@@ -0,0 +1,239 @@
+//! Tests involving `cargo override undo`
+
+use super::manifest::{Dependency, Header, Manifest, Target};
+use super::{create_cargo_manifest, override_path};
+
+use std::path::Path;
+
+use assert_cmd::Command;
+use fs_err as fs;
+use googletest::{
+    expect_eq, expect_that,
+    matchers::{contains_substring, eq, not},
+    verify_eq, verify_that,
+};
+use tempfile::TempDir;
+
+#[googletest::test]
+fn undo_override() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let working_dir_manifest_path = create_project(working_dir);
+
+    let manifest_before = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    override_path("anyhow", working_dir, |command| command)
+        .assert()
+        .success();
+
+    let mut command = undo(working_dir, &[]);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stdout = String::from_utf8(output.stdout.clone()).unwrap();
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r#"
+    Undid `cargo override --path anyhow`
+    "#);
+
+    let manifest_after = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    expect_eq!(manifest_before, manifest_after);
+    expect_that!(working_dir.join("Cargo.lock").exists(), eq(false));
+
+    let mut command = undo(working_dir, &[]);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::assert_snapshot!(stderr, @r#"
+    error: there is nothing to undo
+    "#);
+}
+
+#[googletest::test]
+fn undo_after_other_changes_fails() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let working_dir_manifest_path = create_project(working_dir);
+
+    override_path("anyhow", working_dir, |command| command)
+        .assert()
+        .success();
+
+    let mut edited_manifest = fs::read_to_string(&working_dir_manifest_path).unwrap();
+    edited_manifest.push_str("# an unrelated change\n");
+    fs::write(&working_dir_manifest_path, &edited_manifest).unwrap();
+
+    let mut command = undo(working_dir, &[]);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::with_settings!({filters => vec![
+        (working_dir.to_str().unwrap(), "[TEMPDIR]"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        error: "[TEMPDIR]/Cargo.toml" changed after `cargo override --path anyhow`. Use `--force` to undo it anyway
+        "#);
+    });
+
+    let manifest_after = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    expect_eq!(edited_manifest, manifest_after);
+}
+
+#[googletest::test]
+fn undo_with_conflict_in_older_change_restores_nothing() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let working_dir_manifest_path = create_project(working_dir);
+
+    override_path("anyhow", working_dir, |command| {
+        command.args(["--location", "config"])
+    })
+    .assert()
+    .success();
+
+    let config_path = working_dir.join(".cargo").join("config.toml");
+
+    let mut edited_config = fs::read_to_string(&config_path).unwrap();
+    edited_config.push_str("# an unrelated change\n");
+    fs::write(&config_path, &edited_config).unwrap();
+
+    override_path("anyhow", working_dir, |command| command)
+        .assert()
+        .success();
+
+    let manifest_before = fs::read_to_string(&working_dir_manifest_path).unwrap();
+
+    let mut command = undo(working_dir, &["2"]);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.failure();
+
+    insta::with_settings!({filters => vec![
+        (working_dir.to_str().unwrap(), "[TEMPDIR]"),
+    ]}, {
+        insta::assert_snapshot!(stderr, @r#"
+        error: "[TEMPDIR]/.cargo/config.toml" changed after `cargo override --path anyhow --location config`. Use `--force` to undo it anyway
+        "#);
+    });
+
+    expect_eq!(
+        manifest_before,
+        fs::read_to_string(&working_dir_manifest_path).unwrap()
+    );
+    expect_eq!(edited_config, fs::read_to_string(&config_path).unwrap());
+
+    // The newest change can still be undone on its own
+    undo(working_dir, &[]).assert().success();
+}
+
+/// Listing patches resolves dependencies, which must not count as a change to `Cargo.lock`
+#[googletest::test]
+fn undo_after_list() {
+    let working_dir = TempDir::new().unwrap();
+    let working_dir = working_dir.path();
+
+    let working_dir_manifest_path = create_project(working_dir);
+
+    Command::new("cargo")
+        .current_dir(working_dir)
+        .args(["generate-lockfile", "--quiet"])
+        .env("CARGO_HOME", working_dir)
+        .assert()
+        .success();
+
+    let lock_before = fs::read_to_string(working_dir.join("Cargo.lock")).unwrap();
+
+    // `--locked` leaves `Cargo.lock` behind the patch, for `list` to resolve again
+    override_path("anyhow", working_dir, |command| command.arg("--locked"))
+        .assert()
+        .success();
+
+    Command::cargo_bin("cargo-override")
+        .unwrap()
+        .current_dir(working_dir)
+        .args(["override", "list"])
+        .env("CARGO_HOME", working_dir)
+        .assert()
+        .success();
+
+    let mut command = undo(working_dir, &[]);
+
+    let assert = command.assert();
+
+    let output = assert.get_output();
+
+    let stderr = String::from_utf8(output.stderr.clone()).unwrap();
+
+    assert.success();
+
+    insta::assert_snapshot!(stderr, @r#"
+    Undid `cargo override --path anyhow --locked`
+    "#);
+
+    let lock_after = fs::read_to_string(working_dir.join("Cargo.lock")).unwrap();
+
+    expect_eq!(lock_before, lock_after);
+    expect_that!(
+        fs::read_to_string(&working_dir_manifest_path).unwrap(),
+        not(contains_substring("[patch"))
+    );
+}
+
+fn create_project(working_dir: &Path) -> std::path::PathBuf {
+    let patch_folder_path = working_dir.join("anyhow");
+    fs::create_dir(&patch_folder_path).expect("failed to create patch folder");
+
+    let _ = create_cargo_manifest(
+        &patch_folder_path,
+        &Manifest::new(Header::basic("anyhow").version("1.0.86".to_owned()))
+            .add_target(Target::lib("anyhow", "src/lib.rs"))
+            .render(),
+    );
+
+    let package_name = "package-name";
+    let manifest = Manifest::new(Header::basic(package_name))
+        .add_target(Target::bin(package_name, "src/main.rs"))
+        .add_dependency(Dependency::new("anyhow", "1.0.86"))
+        .render();
+
+    create_cargo_manifest(working_dir, &manifest)
+}
+
+fn undo(working_dir: &Path, args: &[&str]) -> Command {
+    let mut cmd = Command::cargo_bin("cargo-override").unwrap();
+    cmd.current_dir(working_dir)
+        .args(["override", "undo"])
+        .args(args)
+        .env("CARGO_HOME", working_dir)
+        .env_remove("RUST_BACKTRACE");
+
+    cmd
+}